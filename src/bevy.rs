@@ -0,0 +1,136 @@
+/*!
+Bevy integration (`bevy` feature).
+
+`SyncBusPlugin<T>` registers a `Bus<T>` as a non-send resource and bridges it with Bevy
+`Events<T>` both ways:
+
+- messages broadcast on the bus are written as events in `PreUpdate`,
+- events written by systems are broadcast on the bus in `PostUpdate`.
+
+Events coming from the bus are not broadcast back, and broadcasts coming from events are
+not written back as events, so the bridge doesn't echo.
+
+```rust,ignore
+App::new()
+    .add_plugins(SyncBusPlugin::<Value>::new(10))
+    .add_systems(Update, |mut events: EventReader<Value>| {
+        for value in events.read() { ... }
+    });
+```
+*/
+
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use bevy_app::{App, Plugin, PostUpdate, PreUpdate};
+use bevy_ecs::event::{Event, EventReader, EventWriter};
+use bevy_ecs::system::{NonSend, NonSendMut};
+
+use crate::{Bus, BusInner, BusReader};
+
+/// `SyncBusPlugin` inserts a `Bus<T>` non-send resource and the systems bridging it with `Events<T>`.
+pub struct SyncBusPlugin<T> {
+    capacity: usize,
+    _marker: PhantomData<fn() -> T>,
+}
+impl<T> SyncBusPlugin<T> {
+    /// Create the plugin; the `Bus` will be created with `capacity` (2 or more)
+    pub fn new(capacity: usize) -> SyncBusPlugin<T> {
+        SyncBusPlugin::<T> {
+            capacity,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<T: Event + Clone> Plugin for SyncBusPlugin<T> {
+    fn build(&self, app: &mut App) {
+        let mut bus = Bus::<T>::new(self.capacity);
+        let bridge = BusBridge::<T> {
+            reader: bus.add_rx(),
+            inbound: 0..0,
+        };
+        app.add_event::<T>()
+            .insert_non_send_resource(bus)
+            .insert_non_send_resource(bridge)
+            .add_systems(PreUpdate, bus_to_events::<T>)
+            .add_systems(PostUpdate, events_to_bus::<T>);
+    }
+}
+
+/// `BusBridge` is the non-send resource holding the bus reader used by the bridge systems.
+pub struct BusBridge<T: Clone> {
+    reader: BusReader<T>,
+    // ids of the events written from the bus during this frame
+    inbound: Range<usize>,
+}
+
+/// Write the messages broadcast on the bus as `Events<T>`.
+pub fn bus_to_events<T: Event + Clone>(
+    mut bridge: NonSendMut<BusBridge<T>>,
+    mut events: EventWriter<T>,
+) {
+    let batch = bridge.reader.recv();
+    let mut ids = events.write_batch(batch).map(|id| id.id);
+    bridge.inbound = match ids.next() {
+        Some(first) => first..ids.last().unwrap_or(first) + 1,
+        None => 0..0,
+    };
+}
+
+/// Broadcast the `Events<T>` written by systems on the bus.
+pub fn events_to_bus<T: Event + Clone>(bridge: NonSend<BusBridge<T>>, mut events: EventReader<T>) {
+    let reader = &bridge.reader;
+    for (event, id) in events.read_with_id() {
+        if !bridge.inbound.contains(&id.id) {
+            BusInner::publish(&reader.inner, event.clone(), Some(reader.index));
+        }
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy_app::Update;
+    use bevy_ecs::prelude::*;
+
+    #[derive(Event, Copy, Clone, PartialEq, Debug)]
+    enum Value {
+        A,
+        B,
+    }
+
+    #[derive(Resource, Default)]
+    struct Seen(Vec<Value>);
+
+    #[test]
+    fn plugin_should_bridge_bus_and_events() {
+        let mut app = App::new();
+        app.add_plugins(SyncBusPlugin::<Value>::new(10))
+            .init_resource::<Seen>()
+            .add_systems(
+                Update,
+                |mut events: EventReader<Value>, mut seen: ResMut<Seen>| {
+                    seen.0.extend(events.read().copied());
+                },
+            );
+
+        let mut rx = app
+            .world_mut()
+            .non_send_resource_mut::<Bus<Value>>()
+            .add_rx();
+        app.world()
+            .non_send_resource::<Bus<Value>>()
+            .broadcast(Value::A);
+        app.update();
+        assert_eq!(app.world().resource::<Seen>().0, vec![Value::A]);
+        assert_eq!(rx.recv(), vec![Value::A]);
+
+        app.world_mut().send_event(Value::B);
+        app.update();
+        app.update();
+        assert_eq!(app.world().resource::<Seen>().0, vec![Value::A, Value::B]);
+        assert_eq!(rx.recv(), vec![Value::B]);
+    }
+}
@@ -0,0 +1,285 @@
+/*!
+Bridges with thread-safe channels.
+
+Background threads can't use a `Bus`, but they can feed it through a channel:
+
+- `Pump` drains a channel receiver into a bus when `pump()` is called (on the bus thread),
+- `Forwarder` sends the messages of a `BusReader` to a channel sender when `forward()` is called.
+
+Buses of the same thread can be composed with a `Pipe`, which broadcasts the messages
+of a `BusReader` on another bus when pumped, e.g. to gather module buses into an application bus.
+
+`std::sync::mpsc` channels are supported, and `crossbeam-channel` with the `crossbeam` feature.
+
+```rust
+use std::sync::mpsc::channel;
+use std::thread;
+use syncbus::{bridge::Pump, Bus};
+
+let mut bus = Bus::<u32>::new(10);
+let mut rx = bus.add_rx();
+
+let (sender, receiver) = channel();
+thread::spawn(move || sender.send(42).unwrap()).join().unwrap();
+
+let pump = Pump::new(receiver);
+assert_eq!(pump.pump(&bus), 1);
+assert_eq!(rx.recv(), vec![42]);
+```
+*/
+
+use std::rc::{Rc, Weak};
+use std::sync::mpsc;
+
+use crate::{Bus, BusInner, BusReader, Pollable, SendError, Shared};
+
+/// The receiving end of a channel, which can be polled without blocking.
+pub trait ChannelReceiver<T> {
+    /// Return the next message, if any is available now.
+    fn try_next(&self) -> Option<T>;
+}
+impl<T> ChannelReceiver<T> for mpsc::Receiver<T> {
+    fn try_next(&self) -> Option<T> {
+        self.try_recv().ok()
+    }
+}
+
+/// The sending end of a channel.
+pub trait ChannelSender<T> {
+    /// Send a message; fails, returning the message, if the channel is disconnected.
+    fn send_value(&self, value: T) -> Result<(), SendError<T>>;
+}
+impl<T> ChannelSender<T> for mpsc::Sender<T> {
+    fn send_value(&self, value: T) -> Result<(), SendError<T>> {
+        self.send(value).map_err(|err| SendError(err.0))
+    }
+}
+impl<T> ChannelSender<T> for mpsc::SyncSender<T> {
+    fn send_value(&self, value: T) -> Result<(), SendError<T>> {
+        self.send(value).map_err(|err| SendError(err.0))
+    }
+}
+
+#[cfg(feature = "crossbeam")]
+impl<T> ChannelReceiver<T> for crossbeam_channel::Receiver<T> {
+    fn try_next(&self) -> Option<T> {
+        self.try_recv().ok()
+    }
+}
+#[cfg(feature = "crossbeam")]
+impl<T> ChannelSender<T> for crossbeam_channel::Sender<T> {
+    fn send_value(&self, value: T) -> Result<(), SendError<T>> {
+        self.send(value).map_err(|err| SendError(err.into_inner()))
+    }
+}
+
+/// `Pump` moves the messages of a channel receiver into a bus.
+pub struct Pump<R> {
+    receiver: R,
+}
+impl<R> Pump<R> {
+    /// Create a `Pump` draining `receiver`
+    pub fn new(receiver: R) -> Pump<R> {
+        Pump::<R> { receiver }
+    }
+
+    /// Broadcast the messages currently available in the channel, and return how many were pumped.
+    pub fn pump<T: Clone>(&self, bus: &Bus<T>) -> usize
+    where
+        R: ChannelReceiver<T>,
+    {
+        let mut count = 0;
+        while let Some(value) = self.receiver.try_next() {
+            bus.broadcast(value);
+            count += 1;
+        }
+        count
+    }
+
+    /// Release the channel receiver.
+    pub fn into_inner(self) -> R {
+        self.receiver
+    }
+}
+
+/// `Forwarder` moves the messages received by a `BusReader` into a channel sender.
+pub struct Forwarder<T: Clone, S> {
+    reader: BusReader<T>,
+    sender: S,
+}
+impl<T: Clone, S: ChannelSender<T>> Forwarder<T, S> {
+    /// Create a `Forwarder` sending the messages of `reader` to `sender`
+    pub fn new(reader: BusReader<T>, sender: S) -> Forwarder<T, S> {
+        Forwarder::<T, S> { reader, sender }
+    }
+
+    /// Send the pending messages, and return how many were forwarded.
+    /// If the channel is disconnected, the first message which couldn't be sent is returned
+    /// in the error and the rest of the pending messages are dropped.
+    pub fn forward(&mut self) -> Result<usize, SendError<T>> {
+        let mut count = 0;
+        for value in self.reader.recv() {
+            self.sender.send_value(value)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+type Transform<T, U> = Box<dyn FnMut(T) -> Option<U>>;
+
+/// `Pipe` broadcasts the messages of a `BusReader` on another bus, when pumped
+/// (see `Pollable`).
+/// ```
+/// use syncbus::{bridge::Pipe, Bus, Pollable};
+///
+/// let mut audio = Bus::<u32>::new(10);
+/// let mut app = Bus::<String>::new(10);
+/// let mut reader = app.add_rx();
+/// let mut pipe = Pipe::with_transform(audio.add_rx(), &app, |volume| {
+///     Some(format!("volume {}", volume))
+/// });
+/// audio.broadcast(3);
+/// assert_eq!(pipe.pump(), 1);
+/// assert_eq!(reader.recv(), vec!["volume 3"]);
+/// ```
+pub struct Pipe<T: Clone, U: Clone> {
+    reader: BusReader<T>,
+    target: Weak<Shared<U>>,
+    transform: Transform<T, U>,
+}
+impl<T: Clone + 'static> Pipe<T, T> {
+    /// Create a `Pipe` broadcasting the messages of `reader` on `target`
+    pub fn new(reader: BusReader<T>, target: &Bus<T>) -> Pipe<T, T> {
+        Pipe::with_transform(reader, target, Some)
+    }
+}
+impl<T: Clone, U: Clone> Pipe<T, U> {
+    /// Create a `Pipe` converting the messages of `reader` with `transform`, and broadcasting
+    /// the converted ones on `target`; the messages it returns `None` for are skipped.
+    pub fn with_transform<F>(reader: BusReader<T>, target: &Bus<U>, transform: F) -> Pipe<T, U>
+    where
+        F: FnMut(T) -> Option<U> + 'static,
+    {
+        Pipe::<T, U> {
+            reader,
+            target: Rc::downgrade(&target.inner),
+            transform: Box::new(transform),
+        }
+    }
+
+    /// Release the underlying `BusReader`.
+    pub fn into_inner(self) -> BusReader<T> {
+        self.reader
+    }
+}
+impl<T: Clone, U: Clone> Pollable for Pipe<T, U> {
+    /// Broadcast the pending messages on the target bus, and return how many were forwarded;
+    /// the messages stay queued if the target bus was dropped.
+    fn pump(&mut self) -> usize {
+        let target = match self.target.upgrade() {
+            Some(target) if !target.borrow().closed => target,
+            _ => return 0,
+        };
+        let mut count = 0;
+        for value in self.reader.recv() {
+            if let Some(value) = (self.transform)(value) {
+                BusInner::publish(&target, value, None);
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    enum Value {
+        A,
+        B,
+    }
+
+    #[test]
+    fn pump_should_broadcast_channel_messages() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx = bus.add_rx();
+        let (sender, receiver) = mpsc::channel();
+        let pump = Pump::new(receiver);
+
+        assert_eq!(pump.pump(&bus), 0);
+
+        thread::spawn(move || {
+            sender.send(Value::A).unwrap();
+            sender.send(Value::B).unwrap();
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(pump.pump(&bus), 2);
+        assert_eq!(rx.recv(), vec![Value::A, Value::B]);
+    }
+
+    #[test]
+    fn forwarder_should_send_reader_messages() {
+        let mut bus = Bus::<Value>::new(5);
+        let (sender, receiver) = mpsc::channel();
+        let mut forwarder = Forwarder::new(bus.add_rx(), sender);
+
+        bus.broadcast(Value::A);
+        bus.broadcast(Value::B);
+
+        assert_eq!(forwarder.forward(), Ok(2));
+        let received = thread::spawn(move || receiver.iter().take(2).collect::<Vec<_>>());
+        assert_eq!(received.join().unwrap(), vec![Value::A, Value::B]);
+
+        bus.broadcast(Value::A);
+        assert_eq!(forwarder.forward(), Err(SendError(Value::A)));
+    }
+
+    #[test]
+    fn pipes_should_compose_buses() {
+        let mut physics = Bus::<u32>::new(5);
+        let mut input = Bus::<Value>::new(5);
+        let mut app = Bus::<Value>::new(5);
+        let mut rx = app.add_rx();
+        let mut pipes: Vec<Box<dyn Pollable>> = vec![
+            Box::new(Pipe::new(input.add_rx(), &app)),
+            Box::new(Pipe::with_transform(physics.add_rx(), &app, |hits| {
+                Some(Value::B).filter(|_| hits > 0)
+            })),
+        ];
+
+        input.broadcast(Value::A);
+        physics.broadcast(0);
+        physics.broadcast(2);
+        let pumped: Vec<usize> = pipes.iter_mut().map(|pipe| pipe.pump()).collect();
+        assert_eq!(pumped, vec![1, 1]);
+        assert_eq!(rx.recv(), vec![Value::A, Value::B]);
+
+        drop(app);
+        input.broadcast(Value::A);
+        assert_eq!(pipes[0].pump(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "crossbeam")]
+    fn bridges_should_support_crossbeam() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx = bus.add_rx();
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut forwarder = Forwarder::new(bus.add_rx(), sender.clone());
+        let pump = Pump::new(receiver);
+
+        sender.send(Value::A).unwrap();
+        assert_eq!(pump.pump(&bus), 1);
+        assert_eq!(forwarder.forward(), Ok(1));
+        assert_eq!(pump.into_inner().recv(), Ok(Value::A));
+        assert_eq!(rx.recv(), vec![Value::A]);
+    }
+}
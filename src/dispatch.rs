@@ -0,0 +1,330 @@
+//! Callback subscriptions, run by `Bus::dispatch()` or by `HandledReader::pump()`.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::{Bus, BusInner, BusReader, Deferred, HandlerPanic, Pollable, ReaderId, Shared};
+
+// A reader slot whose messages are given to a callback
+pub(crate) struct Handler<T> {
+    pub(crate) index: u64,
+    // Taken out while it runs
+    pub(crate) callback: Option<Box<dyn FnMut(T)>>,
+}
+
+// Unsubscribes a handler whose callback unwinds out of `dispatch()`, losing it
+struct Unwinding<'a, T: Clone> {
+    cell: &'a Shared<T>,
+    index: u64,
+}
+impl<T: Clone> Drop for Unwinding<'_, T> {
+    fn drop(&mut self) {
+        BusInner::detach(self.cell, self.index);
+    }
+}
+
+pub(crate) type PanicHook = Box<dyn FnMut(HandlerPanic)>;
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Box<dyn Any>".to_string(),
+        },
+    }
+}
+
+/// `HandledReader` is a `BusReader` and the handler of its messages, see `Pollable`.
+pub struct HandledReader<T: Clone> {
+    reader: BusReader<T>,
+    handler: Box<dyn FnMut(T)>,
+}
+impl<T: Clone> HandledReader<T> {
+    /// Release the underlying `BusReader`
+    pub fn into_inner(self) -> BusReader<T> {
+        self.reader
+    }
+}
+impl<T: Clone> Pollable for HandledReader<T> {
+    fn pump(&mut self) -> usize {
+        self.reader.poll_each(&mut self.handler)
+    }
+}
+
+impl<T: Clone> BusReader<T> {
+    /// Attach a handler to the reader, called with the messages by `Pollable::pump()`.
+    pub fn with_handler<F: FnMut(T) + 'static>(self, handler: F) -> HandledReader<T> {
+        HandledReader::<T> {
+            reader: self,
+            handler: Box::new(handler),
+        }
+    }
+}
+
+impl<T: Clone> Pollable for Bus<T> {
+    fn pump(&mut self) -> usize {
+        self.dispatch()
+    }
+}
+
+impl<T: Clone> Bus<T> {
+    /// Subscribe a handler: it receives the messages when `dispatch()` is called,
+    /// and its id can be given to `remove_handler()`.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// bus.add_handler(|msg| println!("got {}", msg));
+    /// bus.broadcast(1);
+    /// assert_eq!(bus.dispatch(), 1);
+    /// ```
+    pub fn add_handler<F: FnMut(T) + 'static>(&mut self, handler: F) -> ReaderId {
+        let index = self.inner.next_index();
+        let handler = Handler::<T> {
+            index,
+            callback: Some(Box::new(handler)),
+        };
+        let change = Deferred::Handler(handler);
+        self.inner.deferred.borrow_mut().push_back(change);
+        BusInner::apply_deferred(&self.inner);
+        ReaderId(index)
+    }
+
+    /// Unsubscribe a handler, dropping its pending messages.
+    pub fn remove_handler(&mut self, id: ReaderId) {
+        BusInner::detach(&self.inner, id.0);
+    }
+
+    /// Whether the handler panicked in `dispatch()`, and was removed
+    pub fn handler_panicked(&self, id: ReaderId) -> bool {
+        self.inner.borrow().panicked.contains(&id.0)
+    }
+
+    /// Whether `dispatch()` catches the handler panics (the default), or lets them unwind;
+    /// an unwinding handler is removed, with its pending messages, and the bus stays usable.
+    pub fn catch_panics(&mut self, catch: bool) {
+        self.inner.borrow_mut().catch_panics = catch;
+    }
+
+    /// Register a hook called with the panics caught in `dispatch()`;
+    /// it replaces the previous hook.
+    pub fn on_panic<F: FnMut(HandlerPanic) + 'static>(&mut self, hook: F) {
+        self.inner.borrow_mut().panic_hook = Some(Box::new(hook));
+    }
+
+    /// Give the pending messages to the handlers, and return how many were handled.
+    /// Handlers run one after the other by decreasing priority (see `set_priority()`),
+    /// then in subscription order.
+    ///
+    /// The bus isn't borrowed while handlers run: they can broadcast, the new messages
+    /// are left for the next dispatch. A handler which panics is removed with its pending
+    /// messages, and the panic is passed to the `on_panic()` hook
+    /// (panics can only be caught when built with `panic = "unwind"`).
+    pub fn dispatch(&self) -> usize {
+        let handlers: Vec<u64> = {
+            let inner = self.inner.borrow();
+            let active = |index: u64| inner.handlers.iter().any(|handler| handler.index == index);
+            // in delivery order
+            let slots = inner.slots.iter().map(|rx| rx.index);
+            slots.filter(|index| active(*index)).collect()
+        };
+        let mut count = 0;
+        for index in handlers {
+            let (mut callback, messages, catch) = {
+                let mut inner = self.inner.borrow_mut();
+                let handler = inner.handlers.iter_mut().find(|h| h.index == index);
+                match handler.and_then(|handler| handler.callback.take()) {
+                    Some(callback) => (callback, inner.recv(index), inner.catch_panics),
+                    // removed, or running a nested dispatch
+                    None => continue,
+                }
+            };
+            let unwinding = Unwinding::<T> {
+                cell: &self.inner,
+                index,
+            };
+            let mut panic = None;
+            for value in messages {
+                if !catch {
+                    callback(value);
+                } else if let Err(payload) =
+                    panic::catch_unwind(AssertUnwindSafe(|| callback(value)))
+                {
+                    panic = Some(HandlerPanic {
+                        reader: ReaderId(index),
+                        message: panic_message(payload),
+                    });
+                    break;
+                }
+                count += 1;
+            }
+            std::mem::forget(unwinding);
+
+            let panic = match panic {
+                Some(panic) => panic,
+                None => {
+                    let mut inner = self.inner.borrow_mut();
+                    if let Some(handler) = inner.handlers.iter_mut().find(|h| h.index == index) {
+                        handler.callback = Some(callback);
+                    }
+                    continue;
+                }
+            };
+            drop(callback);
+            BusInner::detach(&self.inner, index);
+            let hook = {
+                let mut inner = self.inner.borrow_mut();
+                inner.panicked.push(index);
+                inner.panic_hook.take()
+            };
+            if let Some(mut hook) = hook {
+                hook(panic);
+                let mut inner = self.inner.borrow_mut();
+                if inner.panic_hook.is_none() {
+                    inner.panic_hook = Some(hook);
+                }
+            }
+        }
+        count
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn dispatch_should_call_handlers() {
+        let mut bus = Bus::<u32>::new(5);
+        let seen = Rc::new(RefCell::new(vec![]));
+        let log = Rc::clone(&seen);
+        let id = bus.add_handler(move |msg| log.borrow_mut().push(msg));
+
+        bus.broadcast(1);
+        bus.broadcast(2);
+        assert_eq!(bus.dispatch(), 2);
+        assert_eq!(*seen.borrow(), vec![1, 2]);
+
+        bus.remove_handler(id);
+        bus.broadcast(3);
+        assert_eq!(bus.dispatch(), 0);
+    }
+
+    #[test]
+    fn dispatch_should_follow_priorities() {
+        let mut bus = Bus::<u32>::new(5);
+        let order = Rc::new(RefCell::new(vec![]));
+        let log = Rc::clone(&order);
+        bus.add_handler(move |_| log.borrow_mut().push("rendering"));
+        let log = Rc::clone(&order);
+        let physics = bus.add_handler(move |_| log.borrow_mut().push("physics"));
+        bus.set_priority(physics, 1);
+
+        bus.broadcast(1);
+        bus.dispatch();
+        assert_eq!(*order.borrow(), vec!["physics", "rendering"]);
+    }
+
+    #[test]
+    fn handlers_should_drop_readers() {
+        let mut bus = Bus::<u32>::new(5);
+        let reader = Rc::new(RefCell::new(Some(bus.add_rx())));
+        let owner = Rc::clone(&reader);
+        bus.add_handler(move |_| drop(owner.borrow_mut().take()));
+
+        bus.broadcast(1);
+        assert_eq!(bus.dispatch(), 1);
+        assert!(reader.borrow().is_none());
+        assert_eq!(bus.inspect().readers.len(), 1);
+    }
+
+    #[test]
+    fn pollables_should_pump_their_messages() {
+        let mut numbers = Bus::<u32>::new(5);
+        let mut words = Bus::<&str>::new(5);
+        let seen = Rc::new(RefCell::new(vec![]));
+        let log = Rc::clone(&seen);
+        let reader = words
+            .add_rx()
+            .with_handler(move |msg| log.borrow_mut().push(msg));
+        let log = Rc::clone(&seen);
+        numbers.add_handler(move |_| log.borrow_mut().push("number"));
+
+        let mut sources: Vec<Box<dyn Pollable>> = vec![Box::new(reader)];
+        numbers.broadcast(1);
+        words.broadcast("one");
+        words.broadcast("two");
+        sources.push(Box::new(numbers));
+
+        let pumped: Vec<usize> = sources.iter_mut().map(|source| source.pump()).collect();
+        assert_eq!(pumped, vec![2, 1]);
+        assert_eq!(*seen.borrow(), vec!["one", "two", "number"]);
+    }
+
+    #[test]
+    fn dispatch_should_catch_handler_panics() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        let panics = Rc::new(RefCell::new(vec![]));
+        let log = Rc::clone(&panics);
+        bus.on_panic(move |panic| log.borrow_mut().push(panic));
+        let faulty = bus.add_handler(|msg| assert!(msg < 2, "too big"));
+
+        bus.broadcast(1);
+        bus.broadcast(2);
+        bus.broadcast(3);
+        assert_eq!(bus.dispatch(), 1);
+
+        assert!(bus.handler_panicked(faulty));
+        assert!(bus.inner.borrow().handlers.is_empty());
+        assert_eq!(panics.borrow().len(), 1);
+        assert_eq!(panics.borrow()[0].reader, faulty);
+        assert_eq!(panics.borrow()[0].message, "too big");
+
+        bus.broadcast(1);
+        assert_eq!(bus.dispatch(), 0);
+        assert_eq!(rx.recv(), vec![1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn unwinding_handlers_should_be_removed() {
+        let mut bus = Bus::<u32>::new(5);
+        bus.catch_panics(false);
+        let faulty = bus.add_handler(|msg| assert!(msg < 2, "too big"));
+
+        bus.broadcast(2);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| bus.dispatch()));
+        assert!(result.is_err());
+        assert!(!bus.handler_panicked(faulty));
+        assert!(bus.inspect().readers.is_empty());
+        assert!(bus.inner.borrow().handlers.is_empty());
+
+        bus.broadcast(1);
+        assert_eq!(bus.dispatch(), 0);
+    }
+
+    #[test]
+    fn handlers_should_be_deferred_while_bus_is_borrowed() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        let old = bus.add_handler(|_| {});
+        bus.broadcast(1);
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let handled = Rc::clone(&log);
+        {
+            let pending = rx.recv_ref();
+            bus.add_handler(move |msg| handled.borrow_mut().push(msg));
+            bus.remove_handler(old);
+            assert_eq!(*pending, [1]);
+        }
+        assert_eq!(bus.inspect().readers.len(), 2);
+
+        bus.broadcast(2);
+        assert_eq!(bus.dispatch(), 1);
+        assert_eq!(*log.borrow(), vec![2]);
+    }
+}
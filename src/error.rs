@@ -0,0 +1,89 @@
+//! Error types of the bus operations.
+
+use std::error::Error;
+use std::fmt;
+
+/// Error returned by `Bus::try_new()` when the capacity is lower than `MIN_CAPACITY`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct CapacityError {
+    /// The rejected capacity
+    pub capacity: usize,
+}
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bus capacity should be at least {}, got {}",
+            crate::MIN_CAPACITY,
+            self.capacity
+        )
+    }
+}
+impl Error for CapacityError {}
+
+/// Error returned by `BusReader::try_recv()`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TryRecvError {
+    /// No message is pending, but the `Bus` may still broadcast some.
+    Empty,
+    /// No message is pending and the `Bus` was dropped: none will ever arrive.
+    Closed,
+}
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(f, "receiving on an empty bus"),
+            TryRecvError::Closed => write!(f, "receiving on a closed bus"),
+        }
+    }
+}
+impl Error for TryRecvError {}
+
+/// Error returned by `BusReader::try_recv_all()`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RecvError {
+    /// No message is pending and the `Bus` was dropped: none will ever arrive.
+    Closed,
+}
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "receiving on a closed bus")
+    }
+}
+impl Error for RecvError {}
+
+/// Error returned by `Bus::try_broadcast()` when no reader would receive the message
+/// (or by a bridge when its channel is disconnected);
+/// the message is given back.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sending without receivers")
+    }
+}
+impl<T> Error for SendError<T> {}
+
+/// Panic caught while `Bus::dispatch()` ran a handler; the handler is removed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HandlerPanic {
+    /// The reader of the handler which panicked
+    pub reader: crate::ReaderId,
+    /// The panic message, when it was a string
+    pub message: String,
+}
+impl fmt::Display for HandlerPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "handler of reader {} panicked: {}",
+            self.reader.0, self.message
+        )
+    }
+}
+impl Error for HandlerPanic {}
@@ -0,0 +1,230 @@
+/*!
+Fixed-capacity bus, which doesn't allocate after construction.
+
+`StaticBus<T, CAP, READERS>` stores up to `READERS` reader queues of `CAP` messages each in
+arrays; when a queue is full the oldest message is dropped. It shares the `Broadcaster` and
+`Receiver` traits with `Bus`.
+
+```rust
+use syncbus::fixed::StaticBus;
+use syncbus::{Broadcaster, Receiver};
+
+let mut bus = StaticBus::<u32, 4, 2>::new();
+let mut rx = bus.add_rx().unwrap();
+
+bus.broadcast(1);
+assert_eq!(rx.try_recv(), Ok(1));
+```
+*/
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::{Broadcaster, Receiver, SendError, TryRecvError};
+
+// Ring buffer queue of a reader
+#[derive(Copy, Clone)]
+struct Ring<T: Copy, const CAP: usize> {
+    buffer: [Option<T>; CAP],
+    head: usize,
+    len: usize,
+    active: bool,
+    dropped: usize,
+}
+impl<T: Copy, const CAP: usize> Ring<T, CAP> {
+    const EMPTY: Ring<T, CAP> = Ring {
+        buffer: [None; CAP],
+        head: 0,
+        len: 0,
+        active: false,
+        dropped: 0,
+    };
+
+    fn push(&mut self, value: T) {
+        if CAP == 0 {
+            self.dropped += 1;
+            return;
+        }
+        if self.len == CAP {
+            self.head = (self.head + 1) % CAP;
+            self.len -= 1;
+            self.dropped += 1;
+        }
+        self.buffer[(self.head + self.len) % CAP] = Some(value);
+        self.len += 1;
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let value = self.buffer[self.head].take();
+        self.head = (self.head + 1) % CAP;
+        self.len -= 1;
+        value
+    }
+}
+
+// Inner state shared by StaticBus and StaticReader
+struct StaticInner<T: Copy, const CAP: usize, const READERS: usize> {
+    rings: [Ring<T, CAP>; READERS],
+    closed: bool,
+}
+
+/// `StaticBus` is the single producer of a fixed-capacity bus:
+/// at most `READERS` readers, each queueing at most `CAP` messages.
+pub struct StaticBus<T: Copy, const CAP: usize, const READERS: usize> {
+    inner: Rc<RefCell<StaticInner<T, CAP, READERS>>>,
+}
+impl<T: Copy, const CAP: usize, const READERS: usize> Drop for StaticBus<T, CAP, READERS> {
+    fn drop(&mut self) {
+        self.inner.borrow_mut().closed = true;
+    }
+}
+impl<T: Copy, const CAP: usize, const READERS: usize> Default for StaticBus<T, CAP, READERS> {
+    fn default() -> Self {
+        StaticBus::new()
+    }
+}
+impl<T: Copy, const CAP: usize, const READERS: usize> StaticBus<T, CAP, READERS> {
+    /// Create a new `StaticBus`; this is its only allocation
+    pub fn new() -> StaticBus<T, CAP, READERS> {
+        let inner = StaticInner {
+            rings: [Ring::EMPTY; READERS],
+            closed: false,
+        };
+        StaticBus {
+            inner: Rc::new(RefCell::new(inner)),
+        }
+    }
+
+    /// Create a new `StaticReader`, or `None` if `READERS` readers already exist.
+    pub fn add_rx(&mut self) -> Option<StaticReader<T, CAP, READERS>> {
+        let mut inner = self.inner.borrow_mut();
+        let index = inner.rings.iter().position(|ring| !ring.active)?;
+        inner.rings[index] = Ring::EMPTY;
+        inner.rings[index].active = true;
+        Some(StaticReader {
+            inner: Rc::clone(&self.inner),
+            index,
+        })
+    }
+
+    /// Number of messages dropped so far, across all the readers, because their queue was full.
+    pub fn dropped(&self) -> usize {
+        self.inner
+            .borrow()
+            .rings
+            .iter()
+            .map(|ring| ring.dropped)
+            .sum()
+    }
+}
+impl<T: Copy, const CAP: usize, const READERS: usize> Broadcaster<T>
+    for StaticBus<T, CAP, READERS>
+{
+    fn broadcast(&self, value: T) {
+        let mut inner = self.inner.borrow_mut();
+        for ring in inner.rings.iter_mut().filter(|ring| ring.active) {
+            ring.push(value);
+        }
+    }
+
+    fn try_broadcast(&self, value: T) -> Result<(), SendError<T>> {
+        if !self.inner.borrow().rings.iter().any(|ring| ring.active) {
+            return Err(SendError(value));
+        }
+        self.broadcast(value);
+        Ok(())
+    }
+}
+
+/// `StaticReader` is a consumer of a `StaticBus`; use `try_recv()` to poll for messages.
+pub struct StaticReader<T: Copy, const CAP: usize, const READERS: usize> {
+    inner: Rc<RefCell<StaticInner<T, CAP, READERS>>>,
+    index: usize,
+}
+impl<T: Copy, const CAP: usize, const READERS: usize> Drop for StaticReader<T, CAP, READERS> {
+    fn drop(&mut self) {
+        self.inner.borrow_mut().rings[self.index].active = false;
+    }
+}
+impl<T: Copy, const CAP: usize, const READERS: usize> Receiver<T>
+    for StaticReader<T, CAP, READERS>
+{
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut inner = self.inner.borrow_mut();
+        match inner.rings[self.index].pop_front() {
+            Some(value) => Ok(value),
+            None if inner.closed => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.borrow().closed
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    enum Value {
+        A,
+        B,
+    }
+
+    #[test]
+    fn static_bus_should_bound_readers() {
+        let mut bus = StaticBus::<Value, 4, 2>::new();
+        assert_eq!(bus.try_broadcast(Value::A), Err(SendError(Value::A)));
+
+        let rx1 = bus.add_rx();
+        let rx2 = bus.add_rx();
+        assert!(rx1.is_some() && rx2.is_some());
+        assert!(bus.add_rx().is_none());
+
+        drop(rx1);
+        assert!(bus.add_rx().is_some());
+    }
+
+    #[test]
+    fn static_reader_should_drop_oldest_messages() {
+        let mut bus = StaticBus::<u32, 2, 2>::new();
+        let mut rx = bus.add_rx().unwrap();
+
+        bus.broadcast(1);
+        bus.broadcast(2);
+        bus.broadcast(3);
+
+        assert_eq!(bus.dropped(), 1);
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(Receiver::recv(&mut rx), vec![3]);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        drop(bus);
+        assert!(rx.is_closed());
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[test]
+    fn buses_should_share_traits() {
+        fn relay(bus: &impl Broadcaster<Value>, rx: &mut impl Receiver<Value>) -> Vec<Value> {
+            bus.broadcast(Value::A);
+            bus.broadcast(Value::B);
+            rx.recv()
+        }
+
+        let mut bus = crate::Bus::<Value>::new(2);
+        let mut rx = bus.add_rx();
+        assert_eq!(relay(&bus, &mut rx), vec![Value::A, Value::B]);
+
+        let mut bus = StaticBus::<Value, 4, 2>::new();
+        let mut rx = bus.add_rx().unwrap();
+        assert_eq!(relay(&bus, &mut rx), vec![Value::A, Value::B]);
+    }
+}
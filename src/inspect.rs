@@ -0,0 +1,110 @@
+//! Snapshots of the bus internal state, for debugging.
+
+#[cfg(feature = "serde")]
+use crate::Bus;
+use crate::ReaderId;
+
+/// `BusState` is returned by `Bus::inspect()`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BusState {
+    /// The subscribed readers, in delivery order (see `Bus::set_priority()`)
+    pub readers: Vec<ReaderState>,
+    /// The topics with a retained value
+    pub retained: Vec<String>,
+    /// The frame counter, see `Bus::tick()`
+    pub tick: u64,
+    /// Number of messages broadcast
+    pub broadcast: u64,
+    /// Whether the `Bus` was dropped
+    pub closed: bool,
+}
+
+/// State of a `BusReader`, in a `BusState`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReaderState {
+    /// The reader identifier
+    pub id: ReaderId,
+    /// The label given with `BusReader::set_label()`
+    pub label: Option<String>,
+    /// The reader group, for readers created with `Bus::add_rx_group()`
+    pub group: Option<String>,
+    /// Number of pending messages
+    pub queue_len: usize,
+    /// Number of messages dropped from the queue because it was full
+    pub dropped: usize,
+    /// The topic, for readers created with `Bus::add_rx_topic()`
+    pub topic: Option<String>,
+    /// The tick of the last poll of the queue (or of the subscription)
+    pub polled_at: u64,
+}
+
+/// `BusStats` is returned by `Bus::stats()`: the bus state, and the throughput since
+/// the previous call.
+#[cfg(feature = "serde")]
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize)]
+pub struct BusStats {
+    /// The bus state
+    #[serde(flatten)]
+    pub state: BusState,
+    /// Number of messages broadcast since the previous call
+    pub broadcast_since: u64,
+    /// Number of ticks since the previous call (see `Bus::tick()`)
+    pub ticks_since: u64,
+}
+
+#[cfg(feature = "serde")]
+impl<T: Clone> Bus<T> {
+    /// Report the state of the bus, and the messages broadcast since the previous call.
+    pub fn stats(&self) -> BusStats {
+        let mut inner = self.inner.borrow_mut();
+        let state = inner.inspect();
+        let (broadcast, tick) = inner.stats_at;
+        inner.stats_at = (state.broadcast, state.tick);
+        BusStats {
+            broadcast_since: state.broadcast - broadcast,
+            ticks_since: state.tick - tick,
+            state,
+        }
+    }
+
+    /// Report the `stats()` in JSON, e.g. for a dev overlay or a telemetry endpoint.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// let mut reader = bus.add_rx();
+    /// reader.set_label("hud");
+    /// bus.broadcast(1);
+    /// let json = bus.stats_json();
+    /// assert!(json.contains(r#""label":"hud","#));
+    /// assert!(json.contains(r#""broadcast_since":1"#));
+    /// ```
+    pub fn stats_json(&self) -> String {
+        serde_json::to_string(&self.stats()).unwrap_or_default()
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(all(test, feature = "serde"))]
+mod test {
+    use crate::Bus;
+
+    #[test]
+    fn stats_should_report_throughput_since_last_call() {
+        let mut bus = Bus::<u32>::new(5);
+        let _rx = bus.add_rx();
+        bus.broadcast(1);
+        bus.broadcast(2);
+        bus.tick();
+
+        let stats = bus.stats();
+        assert_eq!((stats.broadcast_since, stats.ticks_since), (2, 1));
+        assert_eq!(stats.state.readers[0].queue_len, 2);
+
+        bus.broadcast(3);
+        let stats = bus.stats();
+        assert_eq!((stats.broadcast_since, stats.ticks_since), (1, 0));
+        assert_eq!(stats.state.broadcast, 3);
+    }
+}
@@ -0,0 +1,92 @@
+/*!
+egui inspector (`egui` feature).
+
+`show(ui, &bus)` renders the live state of a bus as a collapsible panel: the readers with
+their queue length and drop counter, and the retained topic values. The bus keeps no replay
+buffer, so the recent messages aren't shown, only the last value of each topic.
+
+```rust,ignore
+egui::Window::new("Debug").show(ctx, |ui| {
+    syncbus::inspector::show(ui, &bus);
+});
+```
+*/
+
+use std::fmt::Debug;
+use std::rc::Rc;
+
+use egui::{CollapsingHeader, Grid, Ui};
+
+use crate::Bus;
+
+/// Render the state of `bus` as a collapsible panel (without the recent messages, see above).
+pub fn show<T: Clone + Debug>(ui: &mut Ui, bus: &Bus<T>) {
+    let state = bus.inspect();
+    let title = format!("Bus: {} readers", state.readers.len());
+    // tells apart the widgets of several buses, whichever handle renders them
+    let salt = Rc::as_ptr(&bus.inner);
+    CollapsingHeader::new(title)
+        .id_salt(salt)
+        .default_open(true)
+        .show(ui, |ui| {
+            ui.label(format!(
+                "broadcast: {}, tick: {}{}",
+                state.broadcast,
+                state.tick,
+                if state.closed { ", closed" } else { "" }
+            ));
+
+            Grid::new(("readers", salt)).striped(true).show(ui, |ui| {
+                for header in ["id", "label", "subscription", "queue", "dropped", "polled"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for reader in state.readers.iter() {
+                    ui.label(reader.id.0.to_string());
+                    ui.label(reader.label.as_deref().unwrap_or("-"));
+                    let subscription = match (&reader.group, &reader.topic) {
+                        (Some(group), _) => format!("group {}", group),
+                        (_, Some(topic)) => format!("topic {}", topic),
+                        _ => "-".to_string(),
+                    };
+                    ui.label(subscription);
+                    ui.label(reader.queue_len.to_string());
+                    ui.label(reader.dropped.to_string());
+                    ui.label(reader.polled_at.to_string());
+                    ui.end_row();
+                }
+            });
+
+            if !state.retained.is_empty() {
+                CollapsingHeader::new("Retained").show(ui, |ui| {
+                    for topic in state.retained.iter() {
+                        if let Some(value) = bus.retained(topic) {
+                            ui.monospace(format!("{}: {:?}", topic, value));
+                        }
+                    }
+                });
+            }
+        });
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn inspector_should_render_bus() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        rx.set_label("hud");
+        let _topic = bus.add_rx_topic("ui/#");
+        bus.broadcast_topic("ui/volume", 1);
+
+        let ctx = egui::Context::default();
+        let mut output = ctx.run_ui(egui::RawInput::default(), |ui| show(ui, &bus));
+        output.textures_delta.clear();
+        assert!(!output.shapes.is_empty());
+        assert_eq!(rx.recv(), vec![1]);
+    }
+}
@@ -5,7 +5,7 @@
 
 API is loosely inspired by the `bus` crate:
 
-The `Bus<T: Copy>` struct is the single producer - pass it around to send simple messages.
+The `Bus<T: Clone>` struct is the single producer - pass it around to send simple messages.
 
 Use `bus.add_rx()` to create a new `BusReader`:
 
@@ -16,6 +16,8 @@ Use `bus.add_rx()` to create a new `BusReader`:
 
 ```rust
 use syncbus::Bus;
+# #[derive(Copy, Clone, PartialEq, Debug)]
+# enum Value { A, B }
 
 let mut bus = Bus::<Value>::new(10);
 let mut rx = bus.add_rx();
@@ -25,200 +27,3138 @@ bus.broadcast(Value::B);
 
 assert_eq!(rx.recv(), vec![Value::A, Value::B]);
 ```
+
+## Features
+
+- `smallvec`: store a few messages inline in each reader queue, avoiding heap allocations
+  when readers only see a handful of messages between polls; removing the oldest message
+  (`recv_one()`, overflows, budget evictions) shifts the other ones.
+- `bevy`: `SyncBusPlugin<T>` bridging a `Bus<T>` non-send resource with Bevy `Events<T>`.
+- `crossbeam`: `bridge` support for `crossbeam-channel`, in addition to `std::sync::mpsc`.
+- `derive`: `#[derive(BusEvent)]` on event enums, to subscribe to a single variant
+  with `bus.add_rx_variant::<Payload>()`.
+- `egui`: `syncbus::inspector::show(ui, &bus)` rendering the readers and retained values
+  as a collapsible egui panel.
+- `serde`: `bus.stats_json()` reporting the readers and the throughput, e.g. for a dev overlay.
+- `tokio`: local tasks forwarding tokio broadcast channels into a bus, and mirroring a bus out to them.
+- `yew`: `use_bus_reader` hook polling a reader every animation frame and re-rendering on new messages.
+- `web`: `BusDriver::on_raf(reader, callback)` polling a reader every animation frame,
+  and `EventSource` broadcasting converted DOM events; with `serde`, `TabBridge` mirroring
+  a bus across browser tabs, and `Persistence` saving the pending messages in `localStorage`.
+
+## Testing
+
+The `syncbus::testing` module provides a `MockBus` recording the broadcast messages,
+an `assert_broadcast!(bus, value)` macro, and a `FakeReader` whose queue can be filled directly.
 */
 
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+pub mod bridge;
+mod dispatch;
+pub mod error;
+pub mod fixed;
+pub mod inspect;
+mod map;
+pub mod mux;
+pub mod query;
+pub mod registry;
+mod scope;
+mod split;
+pub mod storage;
+pub mod testing;
+mod timing;
+pub mod topic;
+mod traits;
+mod transaction;
+
+pub use dispatch::HandledReader;
+pub use error::{CapacityError, HandlerPanic, RecvError, SendError, TryRecvError};
+#[cfg(feature = "serde")]
+pub use inspect::BusStats;
+pub use inspect::{BusState, ReaderState};
+pub use map::MapReader;
+pub use scope::OwnerToken;
+pub use split::{BusSender, BusSubscriber};
+pub use storage::{Budget, Eviction, Growth, Overflow, QueueStorage};
+pub use timing::{Debounced, Every, Throttled, TickSource};
+pub use traits::{Broadcaster, BusEvent, Pollable, Receiver, Variant};
+pub use transaction::Transaction;
+
+#[cfg(feature = "derive")]
+pub use syncbus_derive::BusEvent;
+
+use dispatch::{Handler, PanicHook};
+use storage::Storage;
+use topic::TopicTrie;
+
+#[cfg(feature = "bevy")]
+pub mod bevy;
+
+#[cfg(feature = "yew")]
+pub mod yew;
+
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+#[cfg(feature = "egui")]
+pub mod inspector;
+
+#[cfg(feature = "web")]
+pub mod web;
+
+#[cfg(any(feature = "yew", feature = "web"))]
+mod raf;
+
+/// Smallest `Bus` capacity: a bus is meant for several readers,
+/// a single consumer is better served by a plain queue.
+pub const MIN_CAPACITY: usize = 2;
 
-struct RxSlot<T: Copy> {
-    index: usize,
-    queue: Vec<T>,
+/// Number of messages stored inline per reader with the `smallvec` feature
+#[cfg(feature = "smallvec")]
+pub const INLINE_MESSAGES: usize = 4;
+
+// Reader queue: a ring buffer, or inline storage for a few messages with the `smallvec` feature
+#[cfg(feature = "smallvec")]
+type Queue<T> = smallvec::SmallVec<[T; INLINE_MESSAGES]>;
+#[cfg(not(feature = "smallvec"))]
+type Queue<T> = std::collections::VecDeque<T>;
+
+thread_local! {
+    // Last message stamp, shared by all the buses of the thread
+    static SEQUENCE: Cell<u64> = const { Cell::new(0) };
 }
 
-// Inner message bus shared by Bus and BusReader
-struct BusInner<T: Copy> {
-    slots: Vec<RxSlot<T>>,
-    count: usize,
+fn next_stamp() -> u64 {
+    SEQUENCE.with(|sequence| {
+        let stamp = sequence.get() + 1;
+        sequence.set(stamp);
+        stamp
+    })
 }
-impl<T: Copy> BusInner<T> {
-    fn new(capacity: usize) -> BusInner<T> {
-        assert!(capacity > 2, "Capacity should be at least 2");
 
-        BusInner::<T> {
-            slots: Vec::<RxSlot<T>>::with_capacity(capacity),
-            count: 0,
+// Shared by the forks of a reader
+type Filter<T> = Rc<dyn Fn(&T) -> bool>;
+
+struct RxSlot<T: Clone> {
+    index: u64,
+    queue: Storage<T>,
+    // Sequence stamps of the queued messages, popped from the front even with the `smallvec`
+    // feature, hence a ring buffer
+    stamps: VecDeque<u64>,
+    growth: Growth,
+    // Position of the reader group in `BusInner::groups`
+    group: Option<usize>,
+    // Queue bound: the oldest messages are dropped beyond it
+    max_len: Option<usize>,
+    dropped: usize,
+    label: Option<String>,
+    // Readers of a topic filter only receive the messages broadcast on matching topics
+    topic: Option<String>,
+    filter: Option<Filter<T>>,
+    // Bus tick of the last poll
+    polled_at: u64,
+    // Readers with a higher priority are served first
+    priority: i32,
+    // Subscription order, among the readers of the same priority
+    joined: u64,
+    // Whether the queue holds messages, shared with the reader so it can check without
+    // borrowing the bus
+    has_pending: Rc<Cell<bool>>,
+}
+impl<T: Clone> RxSlot<T> {
+    // A slot with the default settings; the bus settings are applied by `BusInner::insert()`
+    fn new(index: u64) -> RxSlot<T> {
+        RxSlot::<T> {
+            index,
+            queue: Storage::Default(Queue::new()),
+            stamps: VecDeque::new(),
+            growth: Growth::Doubling,
+            group: None,
+            max_len: None,
+            dropped: 0,
+            label: None,
+            topic: None,
+            filter: None,
+            polled_at: 0,
+            priority: 0,
+            joined: 0,
+            has_pending: Rc::new(Cell::new(false)),
         }
     }
 
-    fn add_rx(&mut self) -> usize {
-        let index = self.count;
-        self.count += 1;
-        self.slots.push(RxSlot::<T> {
-            index,
-            queue: vec![],
-        });
-        index
+    // Queue the value, moving the messages dropped to make room to `evicted`:
+    // the reader bound drops the oldest messages, the storage bound follows its overflow policy
+    fn push(&mut self, stamp: u64, value: T, evicted: &mut Vec<(ReaderId, T)>) {
+        let id = ReaderId(self.index);
+        let before = evicted.len();
+        if let Some(max_len) = self.max_len {
+            while self.len() >= max_len {
+                match self.pop_front() {
+                    Some((_, old)) => evicted.push((id, old)),
+                    None => break,
+                }
+            }
+        }
+        if let Some(limit) = self.queue.get().limit() {
+            if self.len() >= limit && self.queue.get().overflow() == Overflow::DropNewest {
+                evicted.push((id, value));
+                self.dropped += evicted.len() - before;
+                return;
+            }
+            while self.len() >= limit {
+                match self.pop_front() {
+                    Some((_, old)) => evicted.push((id, old)),
+                    None => break,
+                }
+            }
+        }
+        self.dropped += evicted.len() - before;
+        if self.growth == Growth::Exact {
+            self.queue.get_mut().reserve_exact(1);
+            self.stamps.reserve_exact(1);
+        }
+        self.queue.get_mut().push_back(value);
+        self.stamps.push_back(stamp);
+        self.has_pending.set(true);
+    }
+
+    fn len(&self) -> usize {
+        self.queue.get().len()
+    }
+
+    // Refresh the flag, after messages were removed
+    fn update_pending(&self) {
+        self.has_pending.set(self.len() > 0);
+    }
+
+    fn accepts(&self, value: &T) -> bool {
+        self.filter.as_ref().is_none_or(|filter| filter(value))
+    }
+
+    fn pop_front(&mut self) -> Option<(u64, T)> {
+        let value = self.queue.get_mut().pop_front()?;
+        self.update_pending();
+        Some((self.stamps.pop_front().unwrap_or_default(), value))
+    }
+
+    fn drain_into(&mut self, out: &mut Vec<T>) {
+        self.stamps.clear();
+        self.queue.get_mut().drain_into(out);
+        self.update_pending();
+    }
+
+    fn clear(&mut self) {
+        self.stamps.clear();
+        self.queue.clear();
+        self.update_pending();
+    }
+
+    fn drain(&mut self) -> Vec<T> {
+        let mut out = vec![];
+        self.drain_into(&mut out);
+        out
+    }
+
+    fn drain_stamped(&mut self) -> Vec<(u64, T)> {
+        let mut values = vec![];
+        self.queue.get_mut().drain_into(&mut values);
+        self.update_pending();
+        self.stamps.drain(..).zip(values).collect()
     }
 
-    fn broadcast(&mut self, value: T) {
-        for rx in self.slots.iter_mut() {
-            rx.queue.push(value);
+    // Copies of the queued messages, which stay queued
+    fn peek_stamped(&mut self) -> Vec<(u64, T)> {
+        let messages = self.drain_stamped();
+        for (stamp, value) in messages.iter().cloned() {
+            self.queue.get_mut().push_back(value);
+            self.stamps.push_back(stamp);
         }
+        self.update_pending();
+        messages
     }
 
-    fn recv(&mut self, index: usize) -> Vec<T> {
-        for rx in self.slots.iter_mut() {
-            if rx.index == index {
-                return rx.queue.drain(..).collect();
+    // Remove the queued messages matching `retract`, returning how many were removed
+    fn retract<F: Fn(u64, &T) -> bool>(&mut self, retract: F) -> usize {
+        let before = self.len();
+        for (stamp, value) in self.drain_stamped() {
+            if !retract(stamp, &value) {
+                self.queue.get_mut().push_back(value);
+                self.stamps.push_back(stamp);
             }
         }
-        vec![]
+        self.update_pending();
+        before - self.len()
     }
 
-    fn leave(&mut self, index: usize) {
-        self.slots.retain(|rx| rx.index != index);
+    fn append(&mut self, mut other: RxSlot<T>, evicted: &mut Vec<(ReaderId, T)>) {
+        for (stamp, value) in other.drain_stamped() {
+            self.push(stamp, value, evicted);
+        }
     }
 }
 
-/// `BusReader` is the messages consumer.
-/// Use `recv()` to poll for messages.
-pub struct BusReader<T: Copy> {
-    inner: Rc<RefCell<BusInner<T>>>,
-    index: usize,
+/// `ReaderId` identifies a `BusReader` on its bus.
+///
+/// The slots of unsubscribed readers are reused, but not their ids: the id of a new reader
+/// combines the slot position and the number of times the slot was reused.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct ReaderId(pub u64);
+
+/// `BroadcastReport` is returned by `Bus::broadcast_checked()`, to help the producer
+/// notice readers falling behind.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct BroadcastReport {
+    /// Number of reader queues the message was pushed in
+    pub delivered: usize,
+    /// Number of older messages dropped from bounded queues, or to fit the bus budget
+    pub dropped: usize,
+    /// Length of the deepest reader queue, after the broadcast
+    pub max_queue_len: usize,
+    /// Reader with the deepest queue, if any reader has pending messages
+    pub slowest: Option<ReaderId>,
+}
+
+/// `Subscription` describes a reader subscribing or unsubscribing,
+/// for the `Bus::on_subscribe()` and `Bus::on_unsubscribe()` hooks.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Subscription {
+    /// The reader subscribing or unsubscribing
+    pub reader: ReaderId,
+    /// The reader label, see `BusReader::set_label()` and `Bus::add_rx_labeled()`
+    pub label: Option<String>,
+    /// The number of readers of the bus, after the change
+    pub readers: usize,
+}
+
+/// What an interceptor decides to do with a broadcast message.
+pub enum Intercept<T> {
+    /// Pass the message on to the next interceptor, or to the readers
+    Forward(T),
+    /// Discard the message
+    Drop,
+    /// Pass another message on instead
+    Replace(T),
+}
+
+type Interceptor<T> = Box<dyn FnMut(T) -> Intercept<T>>;
+
+// The interceptors taken out of the bus while they run: put back when dropped, even if one
+// panics, before the ones registered meanwhile and without the ones removed meanwhile
+struct RunningInterceptors<'a, T: Clone> {
+    cell: &'a Shared<T>,
+    interceptors: Vec<(u64, Interceptor<T>)>,
 }
-impl<T: Copy> Drop for BusReader<T> {
+impl<T: Clone> Drop for RunningInterceptors<'_, T> {
     fn drop(&mut self) {
-        self.inner.borrow_mut().leave(self.index);
+        if let Ok(mut inner) = self.cell.try_borrow_mut() {
+            let mut interceptors = std::mem::take(&mut self.interceptors);
+            let removed = std::mem::take(&mut inner.removed_interceptors);
+            interceptors.retain(|(id, _)| !removed.contains(id));
+            let added = std::mem::replace(&mut inner.interceptors, interceptors);
+            inner.interceptors.extend(added);
+        }
     }
 }
-impl<T: Copy> BusReader<T> {
-    /// Receive the pending messages (if any) and empty the queue
-    /// ```
-    /// for msg in reader.recv() {
-    ///     match msg {...}
-    /// }
-    /// ```
-    pub fn recv(&mut self) -> Vec<T> {
-        self.inner.borrow_mut().recv(self.index)
+
+// Readers of a group share the messages: each goes to one member, in turn
+struct Group {
+    name: String,
+    next: usize,
+}
+
+type DropHook<T> = Box<dyn FnMut(ReaderId, T)>;
+type SubscriptionHook = Box<dyn FnMut(&Subscription)>;
+type StateProvider<T> = Box<dyn FnMut() -> Vec<T>>;
+
+// Reader indices hold the slot position in their low bits, and the generation of the slot
+// in their high bits, so that the index of an unsubscribed reader never reaches the next
+// reader of the slot
+const POSITION_BITS: u32 = 32;
+const POSITION_MASK: u64 = (1 << POSITION_BITS) - 1;
+
+fn position(index: u64) -> usize {
+    (index & POSITION_MASK) as usize
+}
+
+// Allocates the reader indices, recycling the positions of the removed slots
+#[derive(Default)]
+struct Indices {
+    // Generation of each position, advanced when the position is released
+    generations: Vec<u32>,
+    free: Vec<usize>,
+}
+
+// Readers receive the messages by decreasing priority, then subscription order
+fn delivery_key<T: Clone>(rx: &RxSlot<T>) -> (std::cmp::Reverse<i32>, u64) {
+    (std::cmp::Reverse(rx.priority), rx.joined)
+}
+
+// The reader slots, by position, and their delivery order
+struct Slots<T: Clone> {
+    entries: Vec<Option<RxSlot<T>>>,
+    // Reader indices by decreasing priority, then subscription order
+    order: Vec<u64>,
+    joined: u64,
+}
+impl<T: Clone> Slots<T> {
+    fn with_capacity(capacity: usize) -> Slots<T> {
+        Slots::<T> {
+            entries: Vec::with_capacity(capacity),
+            order: Vec::with_capacity(capacity),
+            joined: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    fn get(&self, index: u64) -> Option<&RxSlot<T>> {
+        let rx = self.entries.get(position(index))?.as_ref()?;
+        Some(rx).filter(|rx| rx.index == index)
+    }
+
+    fn get_mut(&mut self, index: u64) -> Option<&mut RxSlot<T>> {
+        let rx = self.entries.get_mut(position(index))?.as_mut()?;
+        Some(rx).filter(|rx| rx.index == index)
+    }
+
+    // The slots in delivery order
+    fn iter(&self) -> impl Iterator<Item = &RxSlot<T>> + Clone {
+        self.order.iter().filter_map(move |index| self.get(*index))
+    }
+
+    // The slots in no particular order
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut RxSlot<T>> {
+        self.entries.iter_mut().flatten()
+    }
+
+    fn insert(&mut self, mut rx: RxSlot<T>) {
+        let position = position(rx.index);
+        if self.entries.len() <= position {
+            self.entries.resize_with(position + 1, || None);
+        }
+        self.joined += 1;
+        rx.joined = self.joined;
+        let at = self.find(&rx).unwrap_or_else(|at| at);
+        self.order.insert(at, rx.index);
+        self.entries[position] = Some(rx);
+    }
+
+    fn remove(&mut self, index: u64) -> Option<RxSlot<T>> {
+        let at = self.find(self.get(index)?).ok()?;
+        self.order.remove(at);
+        self.entries[position(index)].take()
+    }
+
+    // The position of the slot in `order`, or where to insert it
+    fn find(&self, rx: &RxSlot<T>) -> Result<usize, usize> {
+        let key = delivery_key(rx);
+        let entries = &self.entries;
+        self.order.binary_search_by(|index| {
+            let other = entries[position(*index)].as_ref();
+            other.map(delivery_key).cmp(&Some(key))
+        })
+    }
+
+    // Change the priority of a reader, moving it in the delivery order
+    fn set_priority(&mut self, index: u64, priority: i32) {
+        let mut rx = match self.remove(index) {
+            Some(rx) => rx,
+            None => return,
+        };
+        rx.priority = priority;
+        let at = self.find(&rx).unwrap_or_else(|at| at);
+        self.order.insert(at, index);
+        self.entries[position(index)] = Some(rx);
     }
 }
 
-/// `Bus` is the single producer.
-/// Use `add_rx()` to create a consumer.
-/// Use `broadcast(value)` to push a message in each consumer queue.
-pub struct Bus<T: Copy> {
-    inner: Rc<RefCell<BusInner<T>>>,
+// Push a copy of the value in each of the target slots, moving it in the last one;
+// returns the delivery report, without the queue lengths
+fn push_all<T: Clone>(
+    slots: &mut Slots<T>,
+    targets: &[u64],
+    value: T,
+    evicted: &mut Vec<(ReaderId, T)>,
+) -> BroadcastReport {
+    let stamp = next_stamp();
+    let before = evicted.len();
+    let mut report = BroadcastReport::default();
+    if let Some((last, others)) = targets.split_last() {
+        for index in others {
+            if let Some(rx) = slots.get_mut(*index) {
+                rx.push(stamp, value.clone(), evicted);
+            }
+        }
+        if let Some(rx) = slots.get_mut(*last) {
+            rx.push(stamp, value, evicted);
+        }
+        report.delivered = targets.len();
+    }
+    report.dropped = evicted.len() - before;
+    report
 }
-impl<T: Copy> Bus<T> {
-    /// Create a new `Bus`, with `capacity` to be 2 or more
-    pub fn new(capacity: usize) -> Bus<T> {
-        let inner = Rc::new(RefCell::new(BusInner::new(capacity)));
-        Bus::<T> { inner }
+
+// How a new reader catches up with the messages broadcast before it subscribed
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum CatchUp {
+    Nothing,
+    // The events of the state provider
+    State,
+    // The retained values of its topic
+    Retained,
+}
+
+// A subscription change made while the bus was borrowed
+enum Deferred<T: Clone> {
+    Subscribe(Box<RxSlot<T>>, Option<String>, CatchUp),
+    // The new slot, the reader it forks, and whether to copy its pending messages
+    Fork(Box<RxSlot<T>>, u64, bool),
+    Handler(Handler<T>),
+    Leave(u64),
+    // Used by `web::TabBridge`
+    #[cfg(any(test, all(feature = "web", feature = "serde")))]
+    RemoveInterceptor(u64),
+}
+
+// State shared by Bus and BusReader
+struct Shared<T: Clone> {
+    inner: RefCell<BusInner<T>>,
+    // Outside of `inner`, to subscribe readers while it is borrowed
+    indices: RefCell<Indices>,
+    // Subscription changes waiting for `inner` to be released
+    deferred: RefCell<VecDeque<Deferred<T>>>,
+}
+impl<T: Clone> Shared<T> {
+    fn new(inner: BusInner<T>) -> Shared<T> {
+        Shared::<T> {
+            inner: RefCell::new(inner),
+            indices: RefCell::new(Indices::default()),
+            deferred: RefCell::new(VecDeque::new()),
+        }
     }
 
-    /// Create a new `BusReader`; it will receive copies of the messages until dropped.
-    pub fn add_rx(&mut self) -> BusReader<T> {
-        BusReader::<T> {
-            inner: Rc::clone(&self.inner),
-            index: self.inner.borrow_mut().add_rx(),
+    fn next_index(&self) -> u64 {
+        let mut indices = self.indices.borrow_mut();
+        let position = match indices.free.pop() {
+            Some(position) => position,
+            None => {
+                indices.generations.push(0);
+                indices.generations.len() - 1
+            }
+        };
+        let position = u32::try_from(position).expect("too many readers");
+        let generation = indices.generations[position as usize];
+        (u64::from(generation) << POSITION_BITS) | u64::from(position)
+    }
+
+    // Recycle the position of a removed slot; a position whose generation is exhausted
+    // is retired instead, so that its indices are never given again
+    fn release_index(&self, index: u64) {
+        let mut indices = self.indices.borrow_mut();
+        let position = position(index);
+        if let Some(generation) = indices.generations[position].checked_add(1) {
+            indices.generations[position] = generation;
+            indices.free.push(position);
         }
     }
+}
+impl<T: Clone> Deref for Shared<T> {
+    type Target = RefCell<BusInner<T>>;
 
-    /// Push copies of the value in the reader queues.
-    pub fn broadcast(&self, value: T) {
-        self.inner.borrow_mut().broadcast(value);
+    fn deref(&self) -> &RefCell<BusInner<T>> {
+        &self.inner
     }
 }
 
-//---------- TESTS ------------
+// Inner message bus
+struct BusInner<T: Clone> {
+    slots: Slots<T>,
+    groups: Vec<Group>,
+    // By id, see `BusInner::add_interceptor()`
+    interceptors: Vec<(u64, Interceptor<T>)>,
+    last_interceptor: u64,
+    // Interceptors removed while they ran
+    removed_interceptors: Vec<u64>,
+    // Drained buffers given back by dropped `Batch`es, ready for reuse
+    pool: Vec<Vec<T>>,
+    // Messages dropped from full queues, waiting for the `on_dropped` hook
+    evicted: Vec<(ReaderId, T)>,
+    // Reused by every broadcast: the group members picked, and the readers receiving it
+    picks: Vec<u64>,
+    targets: Vec<u64>,
+    // Messages sent back by the readers, see `Bus::replies()`
+    replies: Vec<(ReaderId, T)>,
+    // Taken out while it runs
+    on_dropped: Option<DropHook<T>>,
+    has_drop_hook: bool,
+    handlers: Vec<Handler<T>>,
+    // Handlers removed after a caught panic, until `remove_handler()`
+    panicked: Vec<u64>,
+    catch_panics: bool,
+    panic_hook: Option<PanicHook>,
+    // Last stamped value broadcast on each topic
+    retained: BTreeMap<String, (u64, T)>,
+    // Indices of the readers by topic filter
+    topics: TopicTrie,
+    // Catch-up events of the readers subscribed with `subscribe()`; taken out while it runs
+    state_provider: Option<StateProvider<T>>,
+    // Last value given to `broadcast_if_changed()`
+    last_changed: Option<T>,
+    // Frame counter advanced by `Bus::tick()`
+    tick: u64,
+    // Number of messages broadcast
+    broadcast: u64,
+    // Messages broadcast and tick at the last `Bus::stats()`
+    #[cfg(feature = "serde")]
+    stats_at: (u64, u64),
+    budget: Option<(Budget, Eviction)>,
+    // Subscription changes (subscribed or not, reader, label) waiting for the lifecycle hooks
+    subscriptions: Vec<(bool, ReaderId, Option<String>)>,
+    on_subscribe: Option<SubscriptionHook>,
+    on_unsubscribe: Option<SubscriptionHook>,
+    has_lifecycle_hooks: bool,
+    // Set while the lifecycle hooks run
+    notifying: bool,
+    capacity: usize,
+    // Initial capacity of the new reader queues
+    preallocate: usize,
+    growth: Growth,
+    // Number of `Bus` and `BusSender` alive
+    senders: usize,
+    // Set when the `Bus` is dropped
+    closed: bool,
+}
+impl<T: Clone> BusInner<T> {
+    fn new(capacity: usize) -> BusInner<T> {
+        BusInner::<T> {
+            slots: Slots::with_capacity(capacity),
+            groups: vec![],
+            interceptors: vec![],
+            last_interceptor: 0,
+            removed_interceptors: vec![],
+            pool: vec![],
+            evicted: vec![],
+            picks: vec![],
+            targets: vec![],
+            replies: vec![],
+            on_dropped: None,
+            has_drop_hook: false,
+            handlers: vec![],
+            panicked: vec![],
+            catch_panics: true,
+            panic_hook: None,
+            retained: BTreeMap::new(),
+            topics: TopicTrie::default(),
+            state_provider: None,
+            last_changed: None,
+            tick: 0,
+            broadcast: 0,
+            #[cfg(feature = "serde")]
+            stats_at: (0, 0),
+            budget: None,
+            subscriptions: vec![],
+            on_subscribe: None,
+            on_unsubscribe: None,
+            has_lifecycle_hooks: false,
+            notifying: false,
+            capacity,
+            preallocate: 0,
+            growth: Growth::Doubling,
+            senders: 1,
+            closed: false,
+        }
+    }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    // Subscribe the slot, joining `group`; a topic reader gets the matching retained values
+    // in order if `retained`
+    fn insert(&mut self, mut rx: RxSlot<T>, group: Option<&str>, retained: bool) {
+        if let Storage::Default(queue) = &mut rx.queue {
+            queue.reserve_exact(self.preallocate);
+        }
+        rx.stamps.reserve_exact(self.preallocate);
+        rx.growth = self.growth;
+        rx.polled_at = self.tick;
+        rx.group = group.map(|name| self.group(name));
+        if let Some(filter) = &rx.topic {
+            self.topics.insert(filter, rx.index);
+            if retained {
+                let mut values: Vec<(u64, T)> = self
+                    .retained
+                    .iter()
+                    .filter(|(topic, _)| topic::matches(filter, topic))
+                    .map(|(_, retained)| retained.clone())
+                    .collect();
+                values.sort_by_key(|(stamp, _)| *stamp);
+                for (stamp, value) in values {
+                    rx.push(stamp, value, &mut self.evicted);
+                }
+            }
+        }
+        if self.has_lifecycle_hooks {
+            self.subscriptions.push((true, ReaderId(rx.index), None));
+        }
+        self.slots.insert(rx);
+    }
 
-    #[derive(Copy, Clone, PartialEq, Debug)]
-    enum Value {
-        A,
-        B,
+    // Position of the group, created if needed
+    fn group(&mut self, name: &str) -> usize {
+        match self.groups.iter().position(|group| group.name == name) {
+            Some(position) => position,
+            None => {
+                self.groups.push(Group {
+                    name: name.to_string(),
+                    next: 0,
+                });
+                self.groups.len() - 1
+            }
+        }
     }
 
-    #[test]
-    #[should_panic]
-    fn should_enforce_capacity() {
-        let _ = Bus::<Value>::new(1);
+    // Add to `picks` the next member of each group accepted by `eligible`, in turn
+    fn pick_members(&mut self, eligible: impl Fn(&RxSlot<T>) -> bool, picks: &mut Vec<u64>) {
+        let slots = &self.slots;
+        for (position, group) in self.groups.iter_mut().enumerate() {
+            let members = slots.iter().filter(|rx| rx.group == Some(position));
+            let count = members.clone().count();
+            // the eligible member coming first from the member whose turn it is
+            let turns = members.enumerate().filter(|(_, rx)| eligible(rx));
+            let wait = |turn: usize| (turn + count - group.next % count) % count;
+            if let Some((turn, rx)) = turns.min_by_key(|(turn, _)| wait(*turn)) {
+                picks.push(rx.index);
+                group.next = turn + 1;
+            }
+        }
     }
 
-    #[test]
-    fn should_not_crash_broadcasting_without_readers() {
-        let bus = Bus::<Value>::new(10);
-        bus.broadcast(Value::A);
+    fn slot(&self, index: u64) -> Option<&RxSlot<T>> {
+        self.slots.get(index)
     }
 
-    #[test]
-    fn reader_should_have_incremental_head() {
-        let mut bus = Bus::<Value>::new(5);
+    fn slot_mut(&mut self, index: u64) -> Option<&mut RxSlot<T>> {
+        self.slots.get_mut(index)
+    }
+
+    // Run the interceptors then broadcast; the bus isn't borrowed while interceptors run
+    fn publish(cell: &Shared<T>, value: T, except: Option<u64>) -> BroadcastReport {
+        BusInner::publish_on(cell, None, value, except)
+    }
 
+    // Register an interceptor, returning the id to give to `remove_interceptor()`
+    fn add_interceptor(&mut self, interceptor: Interceptor<T>) -> u64 {
+        self.last_interceptor += 1;
+        self.interceptors.push((self.last_interceptor, interceptor));
+        self.last_interceptor
+    }
+
+    // Unregister an interceptor, or defer it until the bus is released
+    #[cfg(any(test, all(feature = "web", feature = "serde")))]
+    fn remove_interceptor(cell: &Shared<T>, id: u64) {
+        let change = Deferred::RemoveInterceptor(id);
+        cell.deferred.borrow_mut().push_back(change);
+        BusInner::apply_deferred(cell);
+    }
+
+    // Run the interceptors, without borrowing the bus; `None` if one dropped the value
+    fn intercept(cell: &Shared<T>, value: T) -> Option<T> {
+        let interceptors = std::mem::take(&mut cell.borrow_mut().interceptors);
+        let mut running = RunningInterceptors::<T> { cell, interceptors };
+        let mut forwarded = Some(value);
+        for (_, interceptor) in running.interceptors.iter_mut() {
+            forwarded = match forwarded.map(interceptor) {
+                Some(Intercept::Forward(value)) | Some(Intercept::Replace(value)) => Some(value),
+                Some(Intercept::Drop) | None => None,
+            };
+        }
+        forwarded
+    }
+
+    // Queue the value for the reader `index` only; gives it back if there is no such reader
+    fn send_to(cell: &Shared<T>, index: u64, value: T) -> Result<(), T> {
+        if cell.borrow().slot(index).is_none() {
+            return Err(value);
+        }
+        let value = match BusInner::intercept(cell, value) {
+            Some(value) => value,
+            None => return Ok(()),
+        };
         {
-            let inner = bus.inner.borrow_mut();
-            assert_eq!(inner.slots.capacity(), 5);
-            assert_eq!(inner.slots.len(), 0);
-            assert_eq!(inner.count, 0);
+            let mut inner = cell.borrow_mut();
+            let inner = &mut *inner;
+            inner.broadcast += 1;
+            match inner.slots.get_mut(index) {
+                Some(rx) => rx.push(next_stamp(), value, &mut inner.evicted),
+                // unsubscribed by an interceptor
+                None => return Err(value),
+            }
+            inner.enforce_budget();
         }
+        BusInner::flush_evicted(cell);
+        BusInner::apply_deferred(cell);
+        Ok(())
+    }
 
-        let mut rxs: Vec<BusReader<Value>> = vec![];
-        for i in 0..10 {
-            let rx = bus.add_rx();
-            assert_eq!(rx.index, i);
-            rxs.push(rx);
+    fn publish_on(
+        cell: &Shared<T>,
+        topic: Option<&str>,
+        value: T,
+        except: Option<u64>,
+    ) -> BroadcastReport {
+        let value = match BusInner::intercept(cell, value) {
+            Some(value) => value,
+            None => return BroadcastReport::default(),
+        };
+        let report = cell.borrow_mut().broadcast_except(topic, value, except);
+        BusInner::flush_evicted(cell);
+        BusInner::apply_deferred(cell);
+        report
+    }
+
+    // Create a plain reader, letting `setup` configure its slot before the state is synchronized
+    fn subscribe(cell: &Rc<Shared<T>>, setup: impl FnOnce(&mut RxSlot<T>)) -> BusReader<T> {
+        let mut rx = RxSlot::new(cell.next_index());
+        setup(&mut rx);
+        BusInner::join(cell, rx, None, CatchUp::State)
+    }
+
+    // Create a reader of a group
+    fn subscribe_group(cell: &Rc<Shared<T>>, group: &str) -> BusReader<T> {
+        let rx = RxSlot::new(cell.next_index());
+        BusInner::join(cell, rx, Some(group.to_string()), CatchUp::Nothing)
+    }
+
+    // Create a reader of a topic filter
+    fn subscribe_topic(cell: &Rc<Shared<T>>, filter: &str) -> BusReader<T> {
+        let mut rx = RxSlot::new(cell.next_index());
+        rx.topic = Some(filter.to_string());
+        BusInner::join(cell, rx, None, CatchUp::Retained)
+    }
+
+    // Subscribe the slot, or defer it until the bus is released (e.g. when subscribing while
+    // a `RecvGuard` is held)
+    fn join(
+        cell: &Rc<Shared<T>>,
+        rx: RxSlot<T>,
+        group: Option<String>,
+        catch_up: CatchUp,
+    ) -> BusReader<T> {
+        let (index, has_pending) = (rx.index, Rc::clone(&rx.has_pending));
+        let change = Deferred::Subscribe(Box::new(rx), group, catch_up);
+        cell.deferred.borrow_mut().push_back(change);
+        BusInner::apply_deferred(cell);
+        BusReader::<T> {
+            inner: Rc::clone(cell),
+            index,
+            has_pending,
         }
+    }
 
-        assert_eq!(Rc::strong_count(&bus.inner), 11);
-        let inner = bus.inner.borrow_mut();
-        assert_eq!(inner.slots.capacity(), 10);
-        assert_eq!(inner.slots.len(), 10);
-        assert_eq!(inner.count, 10);
+    // Subscribe a fork of the reader `index`, or defer it until the bus is released
+    fn join_fork(cell: &Rc<Shared<T>>, index: u64, pending: bool) -> BusReader<T> {
+        let rx = RxSlot::new(cell.next_index());
+        let (fork, has_pending) = (rx.index, Rc::clone(&rx.has_pending));
+        let change = Deferred::Fork(Box::new(rx), index, pending);
+        cell.deferred.borrow_mut().push_back(change);
+        BusInner::apply_deferred(cell);
+        BusReader::<T> {
+            inner: Rc::clone(cell),
+            index: fork,
+            has_pending,
+        }
     }
 
-    #[test]
-    fn reader_should_drop_and_release_count() {
-        let mut bus = Bus::<Value>::new(5);
+    // Unsubscribe the reader (or handler), or defer it until the bus is released
+    fn detach(cell: &Shared<T>, index: u64) {
+        cell.deferred.borrow_mut().push_back(Deferred::Leave(index));
+        BusInner::apply_deferred(cell);
+    }
 
-        for i in 0..10 {
-            let rx = bus.add_rx();
-            assert_eq!(rx.index, i);
+    // Apply the subscription changes, unless the bus is borrowed
+    fn apply_deferred(cell: &Shared<T>) {
+        while cell.try_borrow_mut().is_ok() {
+            let change = cell.deferred.borrow_mut().pop_front();
+            match change {
+                Some(Deferred::Subscribe(rx, group, catch_up)) => {
+                    let index = rx.index;
+                    let retained = catch_up == CatchUp::Retained;
+                    cell.borrow_mut().insert(*rx, group.as_deref(), retained);
+                    if catch_up == CatchUp::State {
+                        BusInner::sync_state(cell, index);
+                    }
+                }
+                Some(Deferred::Fork(rx, index, pending)) => {
+                    cell.borrow_mut().fork(*rx, index, pending);
+                }
+                Some(Deferred::Handler(handler)) => {
+                    let mut inner = cell.borrow_mut();
+                    inner.insert(RxSlot::new(handler.index), None, false);
+                    inner.handlers.push(handler);
+                }
+                Some(Deferred::Leave(index)) => {
+                    let mut inner = cell.borrow_mut();
+                    inner.handlers.retain(|handler| handler.index != index);
+                    inner.panicked.retain(|panicked| *panicked != index);
+                    let removed = inner.leave(index);
+                    drop(inner);
+                    if removed {
+                        cell.release_index(index);
+                    }
+                }
+                #[cfg(any(test, all(feature = "web", feature = "serde")))]
+                Some(Deferred::RemoveInterceptor(id)) => {
+                    let mut inner = cell.borrow_mut();
+                    let count = inner.interceptors.len();
+                    inner.interceptors.retain(|(other, _)| *other != id);
+                    if inner.interceptors.len() == count {
+                        // taken out while running, see `RunningInterceptors`
+                        inner.removed_interceptors.push(id);
+                    }
+                }
+                None => return,
+            }
+            BusInner::flush_subscriptions(cell);
+            BusInner::flush_evicted(cell);
         }
+    }
 
-        assert_eq!(Rc::strong_count(&bus.inner), 1);
-        let inner = bus.inner.borrow_mut();
-        assert_eq!(inner.slots.capacity(), 5);
-        assert_eq!(inner.slots.len(), 0);
-        assert_eq!(inner.count, 10);
+    // A producer is gone: the bus is closed when the last one is
+    fn release_sender(cell: &RefCell<BusInner<T>>) {
+        let mut inner = cell.borrow_mut();
+        inner.senders -= 1;
+        if inner.senders == 0 {
+            inner.closed = true;
+        }
     }
 
-    #[test]
-    fn recv_without_broadcast_should_be_empty() {
-        let mut bus = Bus::<Value>::new(5);
-        let mut rx1 = bus.add_rx();
-        let mut rx2 = bus.add_rx();
+    // Queue the events of the state provider for a new reader; the bus isn't borrowed while it runs
+    fn sync_state(cell: &RefCell<BusInner<T>>, index: u64) {
+        let mut provider = match cell.borrow_mut().state_provider.take() {
+            Some(provider) => provider,
+            None => return,
+        };
+        let events = provider();
+        {
+            let mut inner = cell.borrow_mut();
+            if inner.state_provider.is_none() {
+                inner.state_provider = Some(provider);
+            }
+            let inner = &mut *inner;
+            if let Some(rx) = inner.slots.get_mut(index) {
+                for value in events {
+                    if rx.accepts(&value) {
+                        rx.push(next_stamp(), value, &mut inner.evicted);
+                    }
+                }
+            }
+        }
+        BusInner::flush_evicted(cell);
+    }
 
-        assert_eq!(rx1.recv(), vec![]);
-        assert_eq!(rx2.recv(), vec![]);
+    // Run the lifecycle hooks for the subscription changes, without borrowing the bus;
+    // changes made while the hooks run are handled by the same flush
+    fn flush_subscriptions(cell: &RefCell<BusInner<T>>) {
+        let (mut on_subscribe, mut on_unsubscribe) = {
+            let mut inner = cell.borrow_mut();
+            if inner.notifying || inner.subscriptions.is_empty() {
+                return;
+            }
+            inner.notifying = true;
+            (inner.on_subscribe.take(), inner.on_unsubscribe.take())
+        };
+        loop {
+            let (changes, readers) = {
+                let mut inner = cell.borrow_mut();
+                let changes = std::mem::take(&mut inner.subscriptions);
+                let changes: Vec<_> = changes
+                    .into_iter()
+                    .map(|(subscribed, reader, label)| match subscribed {
+                        // labels can be given right after subscribing
+                        true => (
+                            true,
+                            reader,
+                            inner.slot(reader.0).and_then(|rx| rx.label.clone()),
+                        ),
+                        false => (false, reader, label),
+                    })
+                    .collect();
+                (changes, inner.slots.len())
+            };
+            if changes.is_empty() {
+                break;
+            }
+            for (subscribed, reader, label) in changes {
+                let hook = if subscribed {
+                    &mut on_subscribe
+                } else {
+                    &mut on_unsubscribe
+                };
+                if let Some(hook) = hook {
+                    hook(&Subscription {
+                        reader,
+                        label,
+                        readers,
+                    });
+                }
+            }
+        }
+        let mut inner = cell.borrow_mut();
+        inner.notifying = false;
+        if inner.on_subscribe.is_none() {
+            inner.on_subscribe = on_subscribe;
+        }
+        if inner.on_unsubscribe.is_none() {
+            inner.on_unsubscribe = on_unsubscribe;
+        }
     }
 
-    #[test]
-    fn recv_should_empty_queue_and_return_values() {
-        let mut bus = Bus::<Value>::new(5);
-        let mut rx1 = bus.add_rx();
-        let mut rx2 = bus.add_rx();
+    // Hand the evicted messages to the `on_dropped` hook, without borrowing the bus;
+    // messages evicted while the hook runs are handed over by the same flush
+    fn flush_evicted(cell: &RefCell<BusInner<T>>) {
+        let mut hook = {
+            let mut inner = cell.borrow_mut();
+            if !inner.has_drop_hook {
+                inner.evicted.clear();
+                return;
+            }
+            match inner.on_dropped.take() {
+                Some(hook) => hook,
+                // already running: the outer flush will pick the messages up
+                None => return,
+            }
+        };
+        loop {
+            let evicted = std::mem::take(&mut cell.borrow_mut().evicted);
+            if evicted.is_empty() {
+                break;
+            }
+            for (id, value) in evicted {
+                hook(id, value);
+            }
+        }
+        let mut inner = cell.borrow_mut();
+        if inner.on_dropped.is_none() {
+            inner.on_dropped = Some(hook);
+        }
+    }
 
-        bus.broadcast(Value::A);
-        bus.broadcast(Value::B);
+    // Broadcast, optionally skipping one reader (e.g. a bridge not wanting its own messages back);
+    // messages on a topic are retained, and only reach the matching topic readers and plain readers
+    fn broadcast_except(
+        &mut self,
+        topic: Option<&str>,
+        value: T,
+        except: Option<u64>,
+    ) -> BroadcastReport {
+        self.broadcast += 1;
+        let subscribers = topic.map_or(vec![], |topic| self.topics.matching(topic));
+        let eligible = |rx: &RxSlot<T>| {
+            Some(rx.index) != except
+                && (rx.topic.is_none() || subscribers.contains(&rx.index))
+                && rx.accepts(&value)
+        };
+        let mut picks = std::mem::take(&mut self.picks);
+        let mut targets = std::mem::take(&mut self.targets);
+        picks.clear();
+        self.pick_members(eligible, &mut picks);
+        targets.clear();
+        let receiving = self
+            .slots
+            .iter()
+            .filter(|rx| rx.group.is_none() || picks.contains(&rx.index));
+        targets.extend(receiving.filter(|rx| eligible(rx)).map(|rx| rx.index));
+        let slots = &mut self.slots;
+        let mut report = match topic {
+            Some(topic) => {
+                let report = push_all(slots, &targets, value.clone(), &mut self.evicted);
+                let stamp = SEQUENCE.with(|sequence| sequence.get());
+                self.retained.insert(topic.to_string(), (stamp, value));
+                report
+            }
+            None => push_all(slots, &targets, value, &mut self.evicted),
+        };
+        self.picks = picks;
+        self.targets = targets;
+        report.dropped += self.enforce_budget();
+        report
+    }
 
-        assert_eq!(rx1.recv(), vec![Value::A, Value::B]);
-        assert_eq!(rx2.recv(), vec![Value::A, Value::B]);
+    // Evict messages until the queues fit in the budget, and return how many were evicted
+    fn enforce_budget(&mut self) -> usize {
+        let (limit, eviction) = match self.budget {
+            Some((Budget::Messages(limit), eviction)) => (limit, eviction),
+            Some((Budget::Bytes(bytes), eviction)) => {
+                (bytes / std::mem::size_of::<T>().max(1), eviction)
+            }
+            None => return 0,
+        };
+        let mut total: usize = self.slots.iter().map(|rx| rx.len()).sum();
+        let mut count = 0;
+        while total > limit {
+            let pending = self.slots.iter().filter(|rx| rx.len() > 0);
+            let victim = match eviction {
+                Eviction::LongestQueue => pending.max_by_key(|rx| rx.len()),
+                Eviction::OldestMessage => {
+                    pending.min_by_key(|rx| rx.stamps.iter().next().copied().unwrap_or(u64::MAX))
+                }
+            };
+            let victim = victim.map(|rx| rx.index);
+            let slots = &mut self.slots;
+            let rx = match victim.and_then(|index| slots.get_mut(index)) {
+                Some(rx) => rx,
+                None => break,
+            };
+            if let Some((_, value)) = rx.pop_front() {
+                rx.dropped += 1;
+                self.evicted.push((ReaderId(rx.index), value));
+                count += 1;
+            }
+            total -= 1;
+        }
+        count
+    }
 
-        assert_eq!(rx1.recv(), vec![]);
-        assert_eq!(rx2.recv(), vec![]);
+    // Subscribe the slot `rx` with the settings of the reader `index`, and copies of its
+    // pending messages if `pending`; the group membership, label and custom storage aren't copied
+    fn fork(&mut self, mut rx: RxSlot<T>, index: u64, pending: bool) {
+        if let Some(source) = self.slots.get_mut(index) {
+            rx.max_len = source.max_len;
+            rx.topic = source.topic.clone();
+            rx.filter = source.filter.clone();
+            rx.priority = source.priority;
+            if pending {
+                for (stamp, value) in source.peek_stamped() {
+                    rx.push(stamp, value, &mut self.evicted);
+                }
+            }
+        }
+        self.insert(rx, None, false);
+    }
+
+    // The slot of a reader polling its queue, recording the tick
+    fn poll_slot(&mut self, index: u64) -> Option<&mut RxSlot<T>> {
+        let tick = self.tick;
+        let rx = self.slots.get_mut(index)?;
+        rx.polled_at = tick;
+        Some(rx)
+    }
+
+    fn recv(&mut self, index: u64) -> Vec<T> {
+        match self.poll_slot(index) {
+            Some(rx) => rx.drain(),
+            None => vec![],
+        }
+    }
+
+    // Fill the deepest queue details of the report
+    fn measure(&self, report: &mut BroadcastReport) {
+        for rx in self.slots.iter() {
+            if rx.len() > report.max_queue_len {
+                report.max_queue_len = rx.len();
+                report.slowest = Some(ReaderId(rx.index));
+            }
+        }
+    }
+
+    fn inspect(&self) -> BusState {
+        let readers = self
+            .slots
+            .iter()
+            .map(|rx| ReaderState {
+                id: ReaderId(rx.index),
+                label: rx.label.clone(),
+                group: rx.group.map(|group| self.groups[group].name.clone()),
+                queue_len: rx.len(),
+                dropped: rx.dropped,
+                topic: rx.topic.clone(),
+                polled_at: rx.polled_at,
+            })
+            .collect();
+        BusState {
+            readers,
+            retained: self.retained.keys().cloned().collect(),
+            tick: self.tick,
+            broadcast: self.broadcast,
+            closed: self.closed,
+        }
+    }
+
+    fn recv_stamped(&mut self, index: u64) -> Vec<(u64, T)> {
+        match self.poll_slot(index) {
+            Some(rx) => rx.drain_stamped(),
+            None => vec![],
+        }
+    }
+
+    fn recv_newer_than(&mut self, index: u64, stamp: u64) -> Vec<T> {
+        match self.poll_slot(index) {
+            Some(rx) => rx
+                .drain_stamped()
+                .into_iter()
+                .filter(|(s, _)| *s > stamp)
+                .map(|(_, value)| value)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    fn pop_front(&mut self, index: u64) -> Option<(u64, T)> {
+        self.poll_slot(index).and_then(|rx| rx.pop_front())
+    }
+
+    fn try_recv(&mut self, index: u64) -> Result<T, TryRecvError> {
+        let closed = self.closed;
+        match self.pop_front(index) {
+            Some((_, value)) => Ok(value),
+            None if closed => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    fn recv_pooled(&mut self, index: u64) -> Vec<T> {
+        let mut buffer = self.pool.pop().unwrap_or_default();
+        if let Some(rx) = self.poll_slot(index) {
+            rx.drain_into(&mut buffer);
+        }
+        buffer
+    }
+
+    fn release(&mut self, mut buffer: Vec<T>) {
+        // keep at most one spare buffer per reader
+        if self.pool.len() <= self.slots.len() {
+            buffer.clear();
+            self.pool.push(buffer);
+        }
+    }
+
+    // Remove the slot of the reader; its index can then be released
+    fn leave(&mut self, index: u64) -> bool {
+        let rx = match self.slots.remove(index) {
+            Some(rx) => rx,
+            None => return false,
+        };
+        rx.has_pending.set(false);
+        if self.has_lifecycle_hooks {
+            let label = rx.label.clone();
+            self.subscriptions.push((false, ReaderId(index), label));
+        }
+        if let Some(filter) = &rx.topic {
+            self.topics.remove(filter, index);
+        }
+        // hand the pending messages over to another member of the group
+        if let Some(group) = rx.group {
+            let member = self.slots.iter().find(|m| m.group == Some(group));
+            let member = member.map(|member| member.index);
+            let slots = &mut self.slots;
+            if let Some(member) = member.and_then(|member| slots.get_mut(member)) {
+                member.append(rx, &mut self.evicted);
+            }
+        }
+        true
+    }
+}
+
+/// `BusReader` is the messages consumer.
+/// Use `recv()` to poll for messages.
+pub struct BusReader<T: Clone> {
+    inner: Rc<Shared<T>>,
+    index: u64,
+    // Set when messages are queued, see `has_pending()`
+    has_pending: Rc<Cell<bool>>,
+}
+impl<T: Clone> Drop for BusReader<T> {
+    fn drop(&mut self) {
+        BusInner::detach(&self.inner, self.index);
+    }
+}
+impl<T: Clone> Clone for BusReader<T> {
+    fn clone(&self) -> BusReader<T> {
+        BusInner::join_fork(&self.inner, self.index, true)
+    }
+}
+impl<T: Clone> BusReader<T> {
+    /// The identifier of this reader on its bus
+    pub fn id(&self) -> ReaderId {
+        ReaderId(self.index)
+    }
+
+    /// Name the reader, to tell it apart in `Bus::inspect()` and `Debug` output.
+    pub fn set_label(&mut self, label: &str) {
+        if let Some(rx) = self.inner.borrow_mut().slot_mut(self.index) {
+            rx.label = Some(label.to_string());
+        }
+    }
+
+    /// The label given with `set_label()`
+    pub fn label(&self) -> Option<String> {
+        self.inner
+            .borrow()
+            .slot(self.index)
+            .and_then(|rx| rx.label.clone())
+    }
+
+    /// Receive the pending messages (if any) and empty the queue
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// for msg in reader.recv() {
+    ///     match msg {
+    /// #       _ => {}
+    ///     }
+    /// }
+    /// ```
+    pub fn recv(&mut self) -> Vec<T> {
+        self.inner.borrow_mut().recv(self.index)
+    }
+
+    /// Receive the pending messages one by one, calling `handler` for each, and return how
+    /// many were processed. Messages broadcast by the handler are left for the next poll.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// # bus.broadcast(1);
+    /// let mut total = 0;
+    /// let count = reader.poll_each(|msg| total += msg);
+    /// # assert_eq!((count, total), (1, 1));
+    /// ```
+    pub fn poll_each<F: FnMut(T)>(&mut self, mut handler: F) -> usize {
+        let pending = self.pending();
+        let mut count = 0;
+        while count < pending {
+            // the bus isn't borrowed while the handler runs
+            let next = self.inner.borrow_mut().pop_front(self.index);
+            match next {
+                Some((_, value)) => handler(value),
+                None => break,
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Send a message back to the producer, received with `Bus::replies()`.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<&str>::new(10);
+    /// let mut worker = bus.add_rx();
+    /// bus.broadcast("who can handle X?");
+    /// worker.reply("me");
+    /// assert_eq!(bus.replies(), vec![(worker.id(), "me")]);
+    /// ```
+    pub fn reply(&self, value: T) {
+        let mut inner = self.inner.borrow_mut();
+        inner.replies.push((ReaderId(self.index), value));
+    }
+
+    /// Process the pending messages with `handler` until the queue is empty or the `budget`
+    /// elapsed (at least one message is processed), and return the processed messages
+    /// and whether some were left for the next poll.
+    /// ```
+    /// # use std::time::Duration;
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// bus.broadcast(1);
+    /// let (done, stopped) = reader.recv_for(Duration::from_millis(4), |msg| assert_eq!(*msg, 1));
+    /// assert_eq!((done, stopped), (vec![1], false));
+    /// ```
+    pub fn recv_for<F: FnMut(&T)>(&mut self, budget: Duration, mut handler: F) -> (Vec<T>, bool) {
+        let start = Instant::now();
+        let pending = self.pending();
+        let mut done = Vec::with_capacity(pending);
+        while done.len() < pending {
+            if !done.is_empty() && start.elapsed() >= budget {
+                return (done, true);
+            }
+            // the bus isn't borrowed while the handler runs
+            let next = self.inner.borrow_mut().pop_front(self.index);
+            match next {
+                Some((_, value)) => {
+                    handler(&value);
+                    done.push(value);
+                }
+                None => break,
+            }
+        }
+        (done, false)
+    }
+
+    /// Receive the next pending message, or tell whether the queue is just empty or
+    /// the `Bus` was dropped.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.inner.borrow_mut().try_recv(self.index)
+    }
+
+    /// Receive the oldest pending message, if any.
+    pub fn recv_one(&mut self) -> Option<T> {
+        self.inner
+            .borrow_mut()
+            .pop_front(self.index)
+            .map(|(_, value)| value)
+    }
+
+    /// Receive up to `n` of the oldest pending messages; the others stay queued.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// bus.broadcast(1);
+    /// bus.broadcast(2);
+    /// bus.broadcast(3);
+    /// assert_eq!(reader.recv_n(2), vec![1, 2]);
+    /// assert_eq!(reader.pending(), 1);
+    /// ```
+    pub fn recv_n(&mut self, n: usize) -> Vec<T> {
+        let mut inner = self.inner.borrow_mut();
+        let mut out = Vec::with_capacity(n.min(inner.slot(self.index).map_or(0, |rx| rx.len())));
+        while out.len() < n {
+            match inner.pop_front(self.index) {
+                Some((_, value)) => out.push(value),
+                None => break,
+            }
+        }
+        out
+    }
+
+    /// Number of pending messages
+    pub fn pending(&self) -> usize {
+        self.inner
+            .borrow()
+            .slot(self.index)
+            .map_or(0, |rx| rx.len())
+    }
+
+    /// Whether messages are pending, without borrowing the bus: a cheap check to skip
+    /// the empty readers when polling many of them every frame.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// let mut reader = bus.add_rx();
+    /// assert!(!reader.has_pending());
+    /// bus.broadcast(1);
+    /// assert!(reader.has_pending());
+    /// reader.recv();
+    /// assert!(!reader.has_pending());
+    /// ```
+    pub fn has_pending(&self) -> bool {
+        self.has_pending.get()
+    }
+
+    /// Copies of the pending messages (if any), which stay queued until `commit()`.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// # fn process(_msg: &u32) -> Result<(), ()> { Err(()) }
+    /// bus.broadcast(1);
+    /// bus.broadcast(2);
+    /// let batch = reader.peek_batch();
+    /// let done = batch.iter().take_while(|msg| process(msg).is_ok()).count();
+    /// reader.commit(done);
+    /// // the failed messages are received by the next poll
+    /// assert_eq!(reader.pending(), 2);
+    /// ```
+    pub fn peek_batch(&mut self) -> Vec<T> {
+        let mut inner = self.inner.borrow_mut();
+        let messages = inner.poll_slot(self.index).map(|rx| rx.peek_stamped());
+        let messages = messages.unwrap_or_default().into_iter();
+        messages.map(|(_, value)| value).collect()
+    }
+
+    /// Remove up to `n` of the oldest pending messages, once processed;
+    /// returns how many were removed.
+    pub fn commit(&mut self, n: usize) -> usize {
+        let mut inner = self.inner.borrow_mut();
+        let mut count = 0;
+        while count < n && inner.pop_front(self.index).is_some() {
+            count += 1;
+        }
+        count
+    }
+
+    /// Receive the pending messages (if any) and empty the queue;
+    /// fails if there are none and the `Bus` was dropped.
+    pub fn try_recv_all(&mut self) -> Result<Vec<T>, RecvError> {
+        let mut inner = self.inner.borrow_mut();
+        let messages = inner.recv(self.index);
+        if messages.is_empty() && inner.closed {
+            return Err(RecvError::Closed);
+        }
+        Ok(messages)
+    }
+
+    /// Receive the pending messages (if any) with their sequence stamp, and empty the queue.
+    /// Stamps increase with every broadcast, across all the buses of the thread.
+    pub fn recv_stamped(&mut self) -> Vec<(u64, T)> {
+        self.inner.borrow_mut().recv_stamped(self.index)
+    }
+
+    /// Receive the pending messages stamped after `stamp` (see `Bus::sequence()`),
+    /// and discard the older ones.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// bus.broadcast(1);
+    /// let hidden_at = bus.sequence();
+    /// bus.broadcast(2);
+    /// assert_eq!(reader.recv_newer_than(hidden_at), vec![2]);
+    /// ```
+    pub fn recv_newer_than(&mut self, stamp: u64) -> Vec<T> {
+        self.inner.borrow_mut().recv_newer_than(self.index, stamp)
+    }
+
+    /// Unsubscribe, and return the messages which were still pending
+    /// (e.g. to hand them over to a replacement reader).
+    pub fn into_pending(mut self) -> Vec<T> {
+        self.recv()
+    }
+
+    /// Whether the `Bus` was dropped; pending messages can still be received.
+    pub fn is_closed(&self) -> bool {
+        self.inner.borrow().closed
+    }
+
+    /// Subscribe a new reader with the same filter, topic, bound and priority, receiving
+    /// the messages broadcast from now on; cloning the reader also copies the pending messages.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// bus.broadcast(1);
+    /// let mut fork = reader.fork();
+    /// let mut clone = reader.clone();
+    /// bus.broadcast(2);
+    /// assert_eq!(reader.recv(), vec![1, 2]);
+    /// assert_eq!(fork.recv(), vec![2]);
+    /// assert_eq!(clone.recv(), vec![1, 2]);
+    /// ```
+    pub fn fork(&self) -> BusReader<T> {
+        BusInner::join_fork(&self.inner, self.index, false)
+    }
+
+    /// Borrow the pending messages (if any) without copying them; the queue is emptied
+    /// when the guard is dropped (custom storages are drained in a `Vec` instead).
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// bus.broadcast(1);
+    /// let total: u32 = reader.recv_ref().iter().sum();
+    /// # assert_eq!(total, 1);
+    /// assert_eq!(reader.pending(), 0);
+    /// ```
+    pub fn recv_ref(&mut self) -> RecvGuard<'_, T> {
+        let mut inner = self.inner.borrow_mut();
+        let mut fallback = vec![];
+        if let Some(rx) = inner.poll_slot(self.index) {
+            rx.queue.make_contiguous();
+            if rx.queue.as_slice().is_none() {
+                rx.drain_into(&mut fallback);
+            }
+        }
+        RecvGuard::<T> {
+            shared: &self.inner,
+            inner: Some(inner),
+            index: self.index,
+            fallback,
+        }
+    }
+
+    /// Receive the pending messages (if any) in a `Batch` and empty the queue.
+    /// The batch buffer returns to the bus pool when dropped, so a steady flow of
+    /// messages doesn't allocate.
+    pub fn recv_pooled(&mut self) -> Batch<T> {
+        Batch::<T> {
+            inner: Rc::downgrade(&self.inner),
+            buffer: self.inner.borrow_mut().recv_pooled(self.index),
+        }
+    }
+}
+
+impl<T: Clone> fmt::Debug for BusReader<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("BusReader");
+        debug.field("id", &self.index);
+        // the bus may be borrowed, e.g. when formatting from an interceptor
+        match self.inner.try_borrow() {
+            Ok(inner) => {
+                if let Some(rx) = inner.slot(self.index) {
+                    debug.field("label", &rx.label).field("pending", &rx.len());
+                }
+                debug.field("closed", &inner.closed).finish()
+            }
+            Err(_) => debug.finish_non_exhaustive(),
+        }
+    }
+}
+
+/// `RecvGuard` borrows the pending messages of a reader, received with `recv_ref()`;
+/// it derefs to a slice, and empties the queue when dropped.
+///
+/// The bus is borrowed until the guard is dropped: broadcasting or receiving meanwhile panics,
+/// and readers created or dropped meanwhile only subscribe or unsubscribe once it is dropped.
+pub struct RecvGuard<'a, T: Clone> {
+    shared: &'a Shared<T>,
+    // Released when the guard is dropped
+    inner: Option<RefMut<'a, BusInner<T>>>,
+    index: u64,
+    // Messages of a custom storage, which can't be borrowed as a slice
+    fallback: Vec<T>,
+}
+impl<T: Clone> Drop for RecvGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Some(mut inner) = self.inner.take() {
+            if let Some(rx) = inner.slot_mut(self.index) {
+                rx.clear();
+            }
+        }
+        BusInner::apply_deferred(self.shared);
+    }
+}
+impl<T: Clone> Deref for RecvGuard<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        let inner = self.inner.as_ref();
+        let rx = inner.and_then(|inner| inner.slot(self.index));
+        let queue = rx.and_then(|rx| rx.queue.as_slice());
+        queue.unwrap_or(&self.fallback)
+    }
+}
+
+/// `Batch` holds messages received with `recv_pooled()`; it derefs to a slice.
+/// Its buffer is given back to the bus for reuse when dropped.
+pub struct Batch<T: Clone> {
+    inner: Weak<Shared<T>>,
+    buffer: Vec<T>,
+}
+impl<T: Clone> Drop for Batch<T> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.upgrade() {
+            if let Ok(mut inner) = inner.try_borrow_mut() {
+                inner.release(std::mem::take(&mut self.buffer));
+            }
+        }
+    }
+}
+impl<T: Clone> Deref for Batch<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.buffer
+    }
+}
+impl<'a, T: Clone> IntoIterator for &'a Batch<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.buffer.iter()
+    }
+}
+
+/// `Bus` is the single producer.
+/// Use `add_rx()` to create a consumer.
+/// Use `broadcast(value)` to push a message in each consumer queue.
+pub struct Bus<T: Clone> {
+    inner: Rc<Shared<T>>,
+}
+impl<T: Clone> Drop for Bus<T> {
+    fn drop(&mut self) {
+        BusInner::release_sender(&self.inner);
+    }
+}
+impl<T: Clone> Bus<T> {
+    /// Create a new `Bus`, with `capacity` to be 2 or more.
+    ///
+    /// `capacity` is the number of readers the bus preallocates for;
+    /// more readers can be added, and the reader queues are not bounded by it
+    /// (but they can be preallocated to it, see `set_preallocate()`).
+    ///
+    /// Panics if `capacity` is lower than `MIN_CAPACITY`, see `try_new()`.
+    pub fn new(capacity: usize) -> Bus<T> {
+        match Bus::try_new(capacity) {
+            Ok(bus) => bus,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Create a new `Bus`, or fail if `capacity` is lower than `MIN_CAPACITY`.
+    pub fn try_new(capacity: usize) -> Result<Bus<T>, CapacityError> {
+        if capacity < MIN_CAPACITY {
+            return Err(CapacityError { capacity });
+        }
+        let inner = Rc::new(Shared::new(BusInner::new(capacity)));
+        Ok(Bus::<T> { inner })
+    }
+
+    /// Create a new `BusReader`; it will receive copies of the messages until dropped.
+    pub fn add_rx(&mut self) -> BusReader<T> {
+        BusInner::subscribe(&self.inner, |_| {})
+    }
+
+    /// Create a new `BusReader` storing its pending messages in a custom `QueueStorage`.
+    pub fn add_rx_with<S>(&mut self, storage: S) -> BusReader<T>
+    where
+        S: QueueStorage<T> + 'static,
+    {
+        BusInner::subscribe(&self.inner, |rx| {
+            rx.queue = Storage::Custom(Box::new(storage))
+        })
+    }
+
+    /// Create a new `BusReader` whose queue holds at most `max_len` messages (1 or more):
+    /// when full, the oldest message is dropped to make room.
+    pub fn add_rx_bounded(&mut self, max_len: usize) -> BusReader<T> {
+        BusInner::subscribe(&self.inner, |rx| rx.max_len = Some(max_len.max(1)))
+    }
+
+    /// Create a new labelled `BusReader`, see `BusReader::set_label()`.
+    pub fn add_rx_labeled(&mut self, label: &str) -> BusReader<T> {
+        BusInner::subscribe(&self.inner, |rx| rx.label = Some(label.to_string()))
+    }
+
+    /// Create a new `BusReader` only receiving the messages accepted by `filter`.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// let mut even = bus.add_rx_filtered(|msg| msg % 2 == 0);
+    /// bus.broadcast(1);
+    /// bus.broadcast(2);
+    /// assert_eq!(even.recv(), vec![2]);
+    /// ```
+    pub fn add_rx_filtered<F>(&mut self, filter: F) -> BusReader<T>
+    where
+        F: Fn(&T) -> bool + 'static,
+    {
+        BusInner::subscribe(&self.inner, |rx| rx.filter = Some(Rc::new(filter)))
+    }
+
+    /// Register a state provider: when a reader is created with `add_rx()`, `add_rx_with()`,
+    /// `add_rx_bounded()`, `add_rx_filtered()`, `add_rx_labeled()` or by a `Mux`, the events
+    /// it returns are queued for the new reader only, before the live messages, so that it can
+    /// reconstruct the current state. Group and topic readers, forks and clones don't get them.
+    /// It replaces the previous provider.
+    /// ```
+    /// # use std::{cell::Cell, rc::Rc};
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// let volume = Rc::new(Cell::new(7));
+    /// let current = Rc::clone(&volume);
+    /// bus.on_subscribe_state(move || vec![current.get()]);
+    ///
+    /// let mut reader = bus.add_rx();
+    /// bus.broadcast(8);
+    /// assert_eq!(reader.recv(), vec![7, 8]);
+    /// ```
+    pub fn on_subscribe_state<F>(&mut self, provider: F)
+    where
+        F: FnMut() -> Vec<T> + 'static,
+    {
+        self.inner.borrow_mut().state_provider = Some(Box::new(provider));
+    }
+
+    /// Create a new `BusReader` joining the named `group`; the members of a group receive
+    /// each message in turn, so the group as a whole gets one copy of every message.
+    /// Messages still pending when a member is dropped go to another member.
+    pub fn add_rx_group(&mut self, group: &str) -> BusReader<T> {
+        BusInner::subscribe_group(&self.inner, group)
+    }
+
+    /// Create a new `BusReader` receiving the messages broadcast on the topics matching `filter`
+    /// (see `broadcast_topic()`, and the `topic` module for wildcards); the last value of the
+    /// matching topics, if any, are queued right away.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// bus.broadcast_topic("settings/volume", 7);
+    /// let mut reader = bus.add_rx_topic("settings/volume");
+    /// assert_eq!(reader.recv(), vec![7]);
+    /// ```
+    pub fn add_rx_topic(&mut self, filter: &str) -> BusReader<T> {
+        BusInner::subscribe_topic(&self.inner, filter)
+    }
+
+    /// Push copies of the value in the queues of the readers matching `topic`, and of the readers
+    /// without topic; the value is retained for the readers subscribing to the topic later on.
+    pub fn broadcast_topic(&self, topic: &str, value: T) {
+        BusInner::publish_on(&self.inner, Some(topic), value, None);
+    }
+
+    /// The last value broadcast on `topic`
+    pub fn retained(&self, topic: &str) -> Option<T> {
+        let inner = self.inner.borrow();
+        inner.retained.get(topic).map(|(_, value)| value.clone())
+    }
+
+    /// Forget the last value broadcast on `topic`
+    pub fn clear_retained(&self, topic: &str) {
+        self.inner.borrow_mut().retained.remove(topic);
+    }
+
+    /// Push copies of the value in the reader queues.
+    pub fn broadcast(&self, value: T) {
+        BusInner::publish(&self.inner, value, None);
+    }
+
+    /// Remove the messages matching `retract` from the reader queues, before they are received;
+    /// returns how many copies were removed. Retained topic values are kept.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<&str>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// bus.broadcast("show tooltip");
+    /// bus.broadcast("pointer left");
+    /// bus.retract(|msg| *msg == "show tooltip");
+    /// assert_eq!(reader.recv(), vec!["pointer left"]);
+    /// ```
+    pub fn retract<F: Fn(&T) -> bool>(&self, retract: F) -> usize {
+        let count = {
+            let mut inner = self.inner.borrow_mut();
+            let slots = inner.slots.iter_mut();
+            slots.map(|rx| rx.retract(|_, value| retract(value))).sum()
+        };
+        BusInner::apply_deferred(&self.inner);
+        count
+    }
+
+    /// Remove the message stamped with `stamp` (see `sequence()`) from the reader queues,
+    /// before it is received; returns how many copies were removed.
+    pub fn retract_stamp(&self, stamp: u64) -> usize {
+        let count = {
+            let mut inner = self.inner.borrow_mut();
+            let slots = inner.slots.iter_mut();
+            slots.map(|rx| rx.retract(|s, _| s == stamp)).sum()
+        };
+        BusInner::apply_deferred(&self.inner);
+        count
+    }
+
+    /// The stamp of the latest message broadcast (by any bus of the thread);
+    /// messages broadcast afterwards are stamped with greater values.
+    pub fn sequence(&self) -> u64 {
+        SEQUENCE.with(|sequence| sequence.get())
+    }
+
+    /// Push copies of the value in the reader queues, and report on the state of the queues:
+    /// the deepest queue and its reader, and how many messages were dropped from bounded queues.
+    pub fn broadcast_checked(&self, value: T) -> BroadcastReport {
+        let mut report = BusInner::publish(&self.inner, value, None);
+        self.inner.borrow().measure(&mut report);
+        report
+    }
+
+    /// Push copies of the value in the reader queues; fails, giving the value back,
+    /// if there are no readers.
+    pub fn try_broadcast(&self, value: T) -> Result<(), SendError<T>> {
+        if self.inner.borrow().slots.is_empty() {
+            return Err(SendError(value));
+        }
+        self.broadcast(value);
+        Ok(())
+    }
+
+    /// Receive the messages sent back by the readers with `BusReader::reply()`, in order.
+    pub fn replies(&self) -> Vec<(ReaderId, T)> {
+        std::mem::take(&mut self.inner.borrow_mut().replies)
+    }
+
+    /// Push the value in the queue of the `reader` only, whatever its filter or topic;
+    /// fails, giving the value back, if the reader isn't subscribed.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<&str>::new(10);
+    /// let mut worker = bus.add_rx();
+    /// let mut other = bus.add_rx();
+    /// bus.send_to(worker.id(), "handle X").unwrap();
+    /// assert_eq!(worker.recv(), vec!["handle X"]);
+    /// assert_eq!(other.pending(), 0);
+    /// ```
+    pub fn send_to(&self, reader: ReaderId, value: T) -> Result<(), SendError<T>> {
+        BusInner::send_to(&self.inner, reader.0, value).map_err(SendError)
+    }
+
+    /// Split the bus into a `BusSender`, to broadcast, and a `BusSubscriber`, to add readers;
+    /// the bus is closed when the sender is dropped.
+    /// ```
+    /// # let bus = syncbus::Bus::<u32>::new(10);
+    /// let (sender, mut subscriber) = bus.split();
+    /// let mut reader = subscriber.add_rx();
+    /// sender.broadcast(1);
+    /// assert_eq!(reader.recv(), vec![1]);
+    /// ```
+    pub fn split(self) -> (BusSender<T>, BusSubscriber<T>) {
+        self.inner.borrow_mut().senders += 1;
+        let sender = BusSender::<T> {
+            inner: Rc::clone(&self.inner),
+        };
+        let subscriber = BusSubscriber::<T> {
+            inner: Rc::clone(&self.inner),
+        };
+        (sender, subscriber)
+    }
+
+    /// Register an interceptor: interceptors run in order on every broadcast message,
+    /// before it reaches the reader queues, and can forward, drop or replace it.
+    /// ```
+    /// use syncbus::{Bus, Intercept};
+    ///
+    /// let mut bus = Bus::<u32>::new(10);
+    /// bus.add_interceptor(|msg| if msg > 100 { Intercept::Drop } else { Intercept::Forward(msg) });
+    /// ```
+    pub fn add_interceptor<F>(&mut self, interceptor: F)
+    where
+        F: FnMut(T) -> Intercept<T> + 'static,
+    {
+        self.inner
+            .borrow_mut()
+            .add_interceptor(Box::new(interceptor));
+    }
+
+    /// A snapshot of the bus state: the readers, their queue lengths and labels...
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// let mut reader = bus.add_rx();
+    /// reader.set_label("hud");
+    /// bus.broadcast(1);
+    /// let state = bus.inspect();
+    /// assert_eq!(state.readers[0].label.as_deref(), Some("hud"));
+    /// assert_eq!(state.readers[0].queue_len, 1);
+    /// ```
+    pub fn inspect(&self) -> BusState {
+        self.inner.borrow().inspect()
+    }
+
+    /// Cap the messages buffered across all the reader queues: when a broadcast exceeds
+    /// the budget, messages are evicted according to `eviction`, counted as dropped and
+    /// given to the `on_dropped()` hook.
+    /// ```
+    /// use syncbus::{Budget, Bus, Eviction};
+    ///
+    /// let mut bus = Bus::<u32>::new(10);
+    /// bus.set_budget(Budget::Messages(1000), Eviction::LongestQueue);
+    /// ```
+    pub fn set_budget(&mut self, budget: Budget, eviction: Eviction) {
+        self.inner.borrow_mut().budget = Some((budget, eviction));
+    }
+
+    /// Remove the budget set with `set_budget()`
+    pub fn clear_budget(&mut self) {
+        self.inner.borrow_mut().budget = None;
+    }
+
+    /// Whether the queues of new readers are allocated for `capacity` messages right away,
+    /// rather than growing as messages arrive (the default).
+    pub fn set_preallocate(&mut self, preallocate: bool) {
+        let mut inner = self.inner.borrow_mut();
+        inner.preallocate = if preallocate { inner.capacity } else { 0 };
+    }
+
+    /// How the reader queues grow when full: `Growth::Doubling` by default.
+    pub fn set_growth(&mut self, growth: Growth) {
+        let mut inner = self.inner.borrow_mut();
+        inner.growth = growth;
+        for rx in inner.slots.iter_mut() {
+            rx.growth = growth;
+        }
+    }
+
+    /// Release the memory the reader queues and buffers don't use, e.g. after a traffic spike.
+    pub fn shrink_to_fit(&self) {
+        let mut inner = self.inner.borrow_mut();
+        for rx in inner.slots.iter_mut() {
+            rx.queue.get_mut().shrink_to_fit();
+            rx.stamps.shrink_to_fit();
+        }
+        inner.pool.clear();
+        inner.evicted.shrink_to_fit();
+    }
+
+    /// Advance the frame counter, and return it: call it once per frame (or update)
+    /// to find the readers which stopped polling with `stale_readers()`.
+    pub fn tick(&self) -> u64 {
+        let mut inner = self.inner.borrow_mut();
+        inner.tick += 1;
+        inner.tick
+    }
+
+    /// The readers which haven't polled their queue for more than `threshold` ticks;
+    /// forgotten readers accumulate messages forever.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// let mut forgotten = bus.add_rx();
+    /// forgotten.set_label("forgotten");
+    /// bus.broadcast(1);
+    /// bus.tick();
+    /// bus.tick();
+    /// let stale = bus.stale_readers(1);
+    /// assert_eq!(stale[0].label.as_deref(), Some("forgotten"));
+    /// assert_eq!(stale[0].queue_len, 1);
+    /// ```
+    pub fn stale_readers(&self, threshold: u64) -> Vec<ReaderState> {
+        let BusState { readers, tick, .. } = self.inspect();
+        readers
+            .into_iter()
+            .filter(|reader| tick - reader.polled_at > threshold)
+            .collect()
+    }
+
+    /// Set the priority of a reader (or handler), 0 by default: messages are pushed in the reader
+    /// queues, and handlers called by `dispatch()`, by decreasing priority,
+    /// then in subscription order.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// let rendering = bus.add_handler(|_| { /* draw */ });
+    /// let physics = bus.add_handler(|_| { /* step */ });
+    /// bus.set_priority(physics, 10);
+    /// ```
+    pub fn set_priority(&mut self, reader: ReaderId, priority: i32) {
+        self.inner
+            .borrow_mut()
+            .slots
+            .set_priority(reader.0, priority);
+    }
+
+    /// Register a hook called when a reader subscribes (or a handler is added);
+    /// e.g. to start expensive upstream work once someone listens.
+    /// It replaces the previous hook, and runs with the bus free to use.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// bus.on_subscribe(|sub| if sub.readers == 1 { /* connect */ });
+    /// bus.on_unsubscribe(|sub| if sub.readers == 0 { /* disconnect */ });
+    /// ```
+    pub fn on_subscribe<F: FnMut(&Subscription) + 'static>(&mut self, hook: F) {
+        let mut inner = self.inner.borrow_mut();
+        inner.on_subscribe = Some(Box::new(hook));
+        inner.has_lifecycle_hooks = true;
+    }
+
+    /// Register a hook called when a reader is dropped (or a handler removed);
+    /// it replaces the previous hook.
+    pub fn on_unsubscribe<F: FnMut(&Subscription) + 'static>(&mut self, hook: F) {
+        let mut inner = self.inner.borrow_mut();
+        inner.on_unsubscribe = Some(Box::new(hook));
+        inner.has_lifecycle_hooks = true;
+    }
+
+    /// Register a hook called with every message dropped from a full queue (see
+    /// `add_rx_bounded()` and `QueueStorage::limit()`), and the reader which lost it;
+    /// e.g. to log, or route critical messages to a fallback path.
+    /// It replaces the previous hook, and runs after the broadcast, with the bus free to use.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// bus.on_dropped(|reader, msg| eprintln!("{:?} missed {}", reader, msg));
+    /// ```
+    pub fn on_dropped<F>(&mut self, hook: F)
+    where
+        F: FnMut(ReaderId, T) + 'static,
+    {
+        let mut inner = self.inner.borrow_mut();
+        inner.on_dropped = Some(Box::new(hook));
+        inner.has_drop_hook = true;
+    }
+}
+
+impl<T: Clone> fmt::Debug for Bus<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the bus may be borrowed, e.g. when formatting from an interceptor
+        match self.inner.try_borrow() {
+            Ok(inner) => {
+                let state = inner.inspect();
+                f.debug_struct("Bus")
+                    .field("readers", &state.readers)
+                    .field("retained", &state.retained)
+                    .field("closed", &state.closed)
+                    .finish()
+            }
+            Err(_) => f.debug_struct("Bus").finish_non_exhaustive(),
+        }
+    }
+}
+
+impl<T: Clone> Broadcaster<T> for Bus<T> {
+    fn broadcast(&self, value: T) {
+        Bus::broadcast(self, value);
+    }
+
+    fn try_broadcast(&self, value: T) -> Result<(), SendError<T>> {
+        Bus::try_broadcast(self, value)
+    }
+}
+
+impl<T: Clone> Receiver<T> for BusReader<T> {
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        BusReader::try_recv(self)
+    }
+
+    fn is_closed(&self) -> bool {
+        BusReader::is_closed(self)
+    }
+
+    fn recv(&mut self) -> Vec<T> {
+        BusReader::recv(self)
+    }
+
+    fn poll_each<F: FnMut(T)>(&mut self, handler: F) -> usize {
+        BusReader::poll_each(self, handler)
+    }
+}
+
+impl<T: Clone + PartialEq> Bus<T> {
+    /// Push copies of the value in the reader queues, unless it equals the last value broadcast
+    /// by `broadcast_if_changed()`; returns whether the value was broadcast (values dropped by
+    /// an interceptor aren't remembered).
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// assert!(bus.broadcast_if_changed(1));
+    /// assert!(!bus.broadcast_if_changed(1));
+    /// assert_eq!(reader.recv(), vec![1]);
+    /// ```
+    pub fn broadcast_if_changed(&self, value: T) -> bool {
+        if self.inner.borrow().last_changed.as_ref() == Some(&value) {
+            return false;
+        }
+        let forwarded = match BusInner::intercept(&self.inner, value.clone()) {
+            Some(forwarded) => forwarded,
+            None => return false,
+        };
+        {
+            let mut inner = self.inner.borrow_mut();
+            inner.last_changed = Some(value);
+            inner.broadcast_except(None, forwarded, None);
+        }
+        BusInner::flush_evicted(&self.inner);
+        BusInner::apply_deferred(&self.inner);
+        true
+    }
+}
+
+/// `SharedBus` carries large payloads: each reader receives a `Rc` to the same value.
+pub type SharedBus<T> = Bus<Rc<T>>;
+
+impl<T> Bus<Rc<T>> {
+    /// Wrap the value in a `Rc` once and push pointer copies in the reader queues.
+    pub fn broadcast_shared(&self, value: T) {
+        self.broadcast(Rc::new(value));
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::VecDeque;
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    enum Value {
+        A,
+        B,
+    }
+
+    #[test]
+    #[should_panic(expected = "bus capacity should be at least 2, got 1")]
+    fn should_enforce_capacity() {
+        let _ = Bus::<Value>::new(1);
+    }
+
+    #[test]
+    fn try_new_should_validate_capacity() {
+        assert_eq!(
+            Bus::<Value>::try_new(1).err(),
+            Some(CapacityError { capacity: 1 })
+        );
+        assert!(Bus::<Value>::try_new(MIN_CAPACITY).is_ok());
+    }
+
+    #[test]
+    fn should_not_crash_broadcasting_without_readers() {
+        let bus = Bus::<Value>::new(10);
+        bus.broadcast(Value::A);
+    }
+
+    #[test]
+    fn reader_should_have_incremental_head() {
+        let mut bus = Bus::<Value>::new(5);
+
+        {
+            let inner = bus.inner.borrow_mut();
+            assert_eq!(inner.slots.entries.capacity(), 5);
+            assert_eq!(inner.slots.len(), 0);
+            assert_eq!(bus.inner.indices.borrow().generations.len(), 0);
+        }
+
+        let mut rxs: Vec<BusReader<Value>> = vec![];
+        for i in 0..10 {
+            let rx = bus.add_rx();
+            assert_eq!(rx.index, i);
+            rxs.push(rx);
+        }
+
+        assert_eq!(Rc::strong_count(&bus.inner), 11);
+        let inner = bus.inner.borrow_mut();
+        assert_eq!(inner.slots.entries.capacity(), 10);
+        assert_eq!(inner.slots.len(), 10);
+        assert_eq!(bus.inner.indices.borrow().generations.len(), 10);
+    }
+
+    #[test]
+    fn reader_should_drop_and_release_count() {
+        let mut bus = Bus::<Value>::new(5);
+
+        for i in 0..10 {
+            let rx = bus.add_rx();
+            assert_eq!(rx.index, i << POSITION_BITS);
+        }
+
+        assert_eq!(Rc::strong_count(&bus.inner), 1);
+        let inner = bus.inner.borrow_mut();
+        assert_eq!(inner.slots.entries.capacity(), 5);
+        assert_eq!(inner.slots.len(), 0);
+        assert_eq!(bus.inner.indices.borrow().generations.len(), 1);
+    }
+
+    #[test]
+    fn dropped_reader_slots_should_be_reused() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut first = bus.add_rx();
+        let dropped = bus.add_rx();
+        let stale = dropped.id();
+        drop(dropped);
+
+        let mut last = bus.add_rx();
+        assert_eq!(position(last.index), position(stale.0));
+        assert_ne!(last.id(), stale);
+        assert!(bus.send_to(stale, Value::A).is_err());
+
+        bus.broadcast(Value::B);
+        let readers: Vec<ReaderId> = bus.inspect().readers.iter().map(|r| r.id).collect();
+        assert_eq!(readers, vec![first.id(), last.id()]);
+        assert_eq!(first.recv(), vec![Value::B]);
+        assert_eq!(last.recv(), vec![Value::B]);
+        assert_eq!(bus.inner.indices.borrow().generations.len(), 2);
+    }
+
+    #[test]
+    fn exhausted_slots_should_be_retired() {
+        let mut bus = Bus::<Value>::new(5);
+        drop(bus.add_rx());
+        bus.inner.indices.borrow_mut().generations[0] = u32::MAX;
+        let last = bus.add_rx();
+        let stale = last.id();
+        assert_eq!(stale.0, u64::from(u32::MAX) << POSITION_BITS);
+        drop(last);
+
+        let mut rx = bus.add_rx();
+        assert_eq!(position(rx.index), 1);
+        assert!(bus.send_to(stale, Value::A).is_err());
+        bus.broadcast(Value::B);
+        assert_eq!(rx.recv(), vec![Value::B]);
+        assert!(bus.inner.indices.borrow().free.is_empty());
+    }
+
+    #[test]
+    fn recv_without_broadcast_should_be_empty() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx1 = bus.add_rx();
+        let mut rx2 = bus.add_rx();
+
+        assert_eq!(rx1.recv(), vec![]);
+        assert_eq!(rx2.recv(), vec![]);
+    }
+
+    #[test]
+    fn recv_should_empty_queue_and_return_values() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx1 = bus.add_rx();
+        let mut rx2 = bus.add_rx();
+
+        bus.broadcast(Value::A);
+        bus.broadcast(Value::B);
+
+        assert_eq!(rx1.recv(), vec![Value::A, Value::B]);
+        assert_eq!(rx2.recv(), vec![Value::A, Value::B]);
+
+        assert_eq!(rx1.recv(), vec![]);
+        assert_eq!(rx2.recv(), vec![]);
+    }
+
+    #[test]
+    fn try_broadcast_should_fail_without_readers() {
+        let mut bus = Bus::<Value>::new(5);
+        assert_eq!(bus.try_broadcast(Value::A), Err(SendError(Value::A)));
+
+        let mut rx = bus.add_rx();
+        assert_eq!(bus.try_broadcast(Value::B), Ok(()));
+        assert_eq!(rx.recv(), vec![Value::B]);
+    }
+
+    #[test]
+    fn try_recv_should_tell_empty_from_closed() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx = bus.add_rx();
+
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(rx.try_recv_all(), Ok(vec![]));
+
+        bus.broadcast(Value::A);
+        bus.broadcast(Value::B);
+        drop(bus);
+
+        assert!(rx.is_closed());
+        assert_eq!(rx.try_recv(), Ok(Value::A));
+        assert_eq!(rx.try_recv_all(), Ok(vec![Value::B]));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Closed));
+        assert_eq!(rx.try_recv_all(), Err(RecvError::Closed));
+    }
+
+    #[test]
+    fn recv_n_should_pop_oldest_messages() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        for value in 1..=5 {
+            bus.broadcast(value);
+        }
+
+        assert_eq!(rx.recv_one(), Some(1));
+        assert_eq!(rx.recv_n(2), vec![2, 3]);
+        assert_eq!(rx.pending(), 2);
+        assert_eq!(rx.recv_n(10), vec![4, 5]);
+        assert_eq!(rx.recv_one(), None);
+    }
+
+    #[test]
+    fn group_members_should_share_messages() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx = bus.add_rx();
+        let mut worker1 = bus.add_rx_group("workers");
+        let mut worker2 = bus.add_rx_group("workers");
+        let mut other = bus.add_rx_group("others");
+
+        bus.broadcast(Value::A);
+        bus.broadcast(Value::B);
+        bus.broadcast(Value::A);
+
+        assert_eq!(rx.recv(), vec![Value::A, Value::B, Value::A]);
+        assert_eq!(worker1.recv(), vec![Value::A, Value::A]);
+        assert_eq!(worker2.recv(), vec![Value::B]);
+        assert_eq!(other.recv(), vec![Value::A, Value::B, Value::A]);
+    }
+
+    #[test]
+    fn group_member_leaving_should_hand_over_messages() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut worker1 = bus.add_rx_group("workers");
+        let worker2 = bus.add_rx_group("workers");
+
+        bus.broadcast(Value::A);
+        bus.broadcast(Value::B);
+        drop(worker2);
+        bus.broadcast(Value::A);
+
+        assert_eq!(worker1.recv(), vec![Value::A, Value::B, Value::A]);
+    }
+
+    #[test]
+    fn group_should_skip_members_not_accepting_message() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut worker1 = bus.add_rx_group("workers");
+        let mut worker2 = bus.add_rx_group("workers");
+        let only_a = |value: &Value| *value == Value::A;
+        bus.inner
+            .borrow_mut()
+            .slot_mut(worker2.index)
+            .unwrap()
+            .filter = Some(Rc::new(only_a));
+
+        BusInner::publish(&bus.inner, Value::A, Some(worker1.index));
+        bus.broadcast(Value::B);
+        bus.broadcast(Value::A);
+        bus.broadcast(Value::A);
+
+        assert_eq!(worker1.recv(), vec![Value::B, Value::A]);
+        assert_eq!(worker2.recv(), vec![Value::A, Value::A]);
+    }
+
+    #[test]
+    fn interceptors_should_run_in_order() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        let log = Rc::new(RefCell::new(vec![]));
+
+        let first_log = Rc::clone(&log);
+        bus.add_interceptor(move |msg| {
+            first_log.borrow_mut().push(msg);
+            match msg {
+                0 => Intercept::Drop,
+                1 => Intercept::Replace(10),
+                _ => Intercept::Forward(msg),
+            }
+        });
+        let second_log = Rc::clone(&log);
+        bus.add_interceptor(move |msg| {
+            second_log.borrow_mut().push(msg);
+            Intercept::Forward(msg + 1)
+        });
+
+        bus.broadcast(0);
+        bus.broadcast(1);
+        bus.broadcast(2);
+
+        assert_eq!(rx.recv(), vec![11, 3]);
+        assert_eq!(*log.borrow(), vec![0, 1, 10, 2, 2]);
+    }
+
+    #[test]
+    fn interceptors_should_survive_a_panic() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        bus.add_interceptor(|msg| match msg {
+            666 => Intercept::Drop,
+            _ => Intercept::Forward(msg),
+        });
+        bus.add_interceptor(|msg| {
+            assert!(msg != 1, "unlucky");
+            Intercept::Forward(msg)
+        });
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| bus.broadcast(1)));
+        assert!(result.is_err());
+        bus.broadcast(666);
+        bus.broadcast(2);
+        assert_eq!(rx.recv(), vec![2]);
+        assert_eq!(bus.inner.borrow().interceptors.len(), 2);
+    }
+
+    #[test]
+    fn interceptors_should_be_removed() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        let cell = Rc::downgrade(&bus.inner);
+        let once = bus.inner.borrow_mut().add_interceptor(Box::new(move |msg| {
+            if let Some(cell) = cell.upgrade() {
+                BusInner::remove_interceptor(&cell, 1);
+            }
+            Intercept::Replace(msg + 10)
+        }));
+        let double = bus
+            .inner
+            .borrow_mut()
+            .add_interceptor(Box::new(|msg| Intercept::Replace(msg * 2)));
+        assert_eq!(once, 1);
+
+        bus.broadcast(1);
+        bus.broadcast(2);
+        BusInner::remove_interceptor(&bus.inner, double);
+        bus.broadcast(3);
+        assert_eq!(rx.recv(), vec![22, 4, 3]);
+        assert!(bus.inner.borrow().interceptors.is_empty());
+    }
+
+    #[test]
+    fn bounded_reader_should_drop_oldest_messages() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx_bounded(2);
+
+        bus.broadcast(1);
+        bus.broadcast(2);
+        bus.broadcast(3);
+
+        assert_eq!(rx.recv(), vec![2, 3]);
+    }
+
+    #[test]
+    fn topic_readers_should_receive_retained_value() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut plain = bus.add_rx();
+        let mut early = bus.add_rx_topic("settings/volume");
+
+        bus.broadcast_topic("settings/volume", 3);
+        bus.broadcast_topic("settings/volume", 7);
+        bus.broadcast_topic("settings/theme", 1);
+        bus.broadcast(0);
+
+        let mut late = bus.add_rx_topic("settings/volume");
+        assert_eq!(early.recv(), vec![3, 7]);
+        assert_eq!(late.recv(), vec![7]);
+        assert_eq!(plain.recv(), vec![3, 7, 1, 0]);
+        assert_eq!(bus.retained("settings/theme"), Some(1));
+        assert_eq!(
+            bus.inspect().retained,
+            vec!["settings/theme", "settings/volume"]
+        );
+
+        bus.clear_retained("settings/volume");
+        assert!(bus.add_rx_topic("settings/volume").recv().is_empty());
+    }
+
+    #[test]
+    fn wildcard_readers_should_only_queue_matching_messages() {
+        let mut bus = Bus::<u32>::new(5);
+        bus.broadcast_topic("ui/slider/moved", 1);
+        let mut clicks = bus.add_rx_topic("ui/+/clicked");
+        let mut ui = bus.add_rx_topic("ui/#");
+
+        bus.broadcast_topic("ui/button/clicked", 2);
+        bus.broadcast_topic("ui/button/hovered", 3);
+        bus.broadcast_topic("settings/volume", 4);
+        drop(clicks.recv());
+        drop(clicks);
+        bus.broadcast_topic("ui/toggle/clicked", 5);
+
+        assert_eq!(ui.recv(), vec![1, 2, 3, 5]);
+        let topics = &bus.inner.borrow().topics;
+        assert_eq!(topics.matching("ui/toggle/clicked"), vec![ui.id().0]);
+    }
+
+    #[test]
+    fn new_readers_should_receive_subscribe_state() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut early = bus.add_rx();
+        bus.on_subscribe_state(|| vec![1, 2, 3]);
+
+        let mut late = bus.add_rx();
+        let mut bounded = bus.add_rx_bounded(2);
+        bus.broadcast(4);
+
+        assert_eq!(early.recv(), vec![4]);
+        assert_eq!(late.recv(), vec![1, 2, 3, 4]);
+        assert_eq!(bounded.recv(), vec![3, 4]);
+    }
+
+    #[test]
+    fn stale_readers_should_report_readers_not_polling() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut active = bus.add_rx();
+        let idle = bus.add_rx();
+
+        bus.broadcast(Value::A);
+        for _ in 0..3 {
+            bus.tick();
+            bus.broadcast(Value::B);
+            active.recv();
+        }
+
+        let stale = bus.stale_readers(2);
+        assert_eq!(stale.len(), 1);
+        assert_eq!((stale[0].id, stale[0].queue_len), (idle.id(), 4));
+        assert!(bus.stale_readers(3).is_empty());
+    }
+
+    #[test]
+    fn inspect_should_describe_readers() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx1 = bus.add_rx();
+        let rx2 = bus.add_rx_group("workers");
+        rx1.set_label("hud");
+
+        bus.broadcast(Value::A);
+
+        assert_eq!(rx1.label(), Some("hud".to_string()));
+        assert_eq!(
+            bus.inspect(),
+            BusState {
+                readers: vec![
+                    ReaderState {
+                        id: rx1.id(),
+                        label: Some("hud".to_string()),
+                        group: None,
+                        queue_len: 1,
+                        dropped: 0,
+                        topic: None,
+                        polled_at: 0,
+                    },
+                    ReaderState {
+                        id: rx2.id(),
+                        label: None,
+                        group: Some("workers".to_string()),
+                        queue_len: 1,
+                        dropped: 0,
+                        topic: None,
+                        polled_at: 0,
+                    },
+                ],
+                retained: vec![],
+                tick: 0,
+                broadcast: 1,
+                closed: false,
+            }
+        );
+        assert_eq!(
+            format!("{:?}", rx1),
+            "BusReader { id: 0, label: Some(\"hud\"), pending: 1, closed: false }"
+        );
+        assert!(format!("{:?}", bus).starts_with("Bus { readers: [ReaderState { id: ReaderId(0)"));
+    }
+
+    #[test]
+    fn budget_should_evict_from_longest_queue() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut fast = bus.add_rx();
+        let mut slow = bus.add_rx();
+        let dropped = Rc::new(RefCell::new(vec![]));
+        let log = Rc::clone(&dropped);
+        bus.on_dropped(move |reader, msg| log.borrow_mut().push((reader, msg)));
+        bus.set_budget(Budget::Messages(4), Eviction::LongestQueue);
+
+        bus.broadcast(1);
+        fast.recv();
+        bus.broadcast(2);
+        let report = bus.broadcast_checked(3);
+
+        assert_eq!(report.dropped, 1);
+        assert_eq!(*dropped.borrow(), vec![(slow.id(), 1)]);
+        assert_eq!(slow.recv(), vec![2, 3]);
+        assert_eq!(fast.recv(), vec![2, 3]);
+    }
+
+    #[test]
+    fn budget_should_evict_oldest_message() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut first = bus.add_rx_bounded(1);
+        bus.broadcast(1);
+        let mut second = bus.add_rx();
+        bus.set_budget(
+            Budget::Bytes(3 * std::mem::size_of::<u32>()),
+            Eviction::OldestMessage,
+        );
+
+        bus.broadcast(2);
+        bus.broadcast(3);
+        bus.broadcast(4);
+
+        assert_eq!(first.recv(), vec![4]);
+        assert_eq!(second.recv(), vec![3, 4]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "smallvec"))]
+    fn queues_should_follow_allocation_policies() {
+        fn queue_capacity(bus: &Bus<u32>, reader: &BusReader<u32>) -> usize {
+            match &bus.inner.borrow().slot(reader.index).unwrap().queue {
+                Storage::Default(queue) => queue.capacity(),
+                Storage::Custom(_) => 0,
+            }
+        }
+
+        let mut bus = Bus::<u32>::new(8);
+        bus.set_preallocate(true);
+        let mut rx = bus.add_rx();
+        assert!(queue_capacity(&bus, &rx) >= 8);
+
+        bus.set_growth(Growth::Exact);
+        for value in 0..20 {
+            bus.broadcast(value);
+        }
+        assert_eq!(queue_capacity(&bus, &rx), 20);
+
+        rx.recv_n(15);
+        bus.shrink_to_fit();
+        assert_eq!(queue_capacity(&bus, &rx), 5);
+    }
+
+    #[test]
+    fn readers_should_be_served_by_priority() {
+        let mut bus = Bus::<u32>::new(5);
+        let order = Rc::new(RefCell::new(vec![]));
+        let readers: Vec<_> = (0..3).map(|_| bus.add_rx_bounded(1)).collect();
+        let log = Rc::clone(&order);
+        bus.on_dropped(move |reader, _| log.borrow_mut().push(reader));
+        bus.set_priority(readers[2].id(), 1);
+        bus.set_priority(readers[0].id(), -1);
+
+        bus.broadcast(1);
+        bus.broadcast(2);
+        let ids: Vec<_> = readers.iter().map(|reader| reader.id()).collect();
+        assert_eq!(*order.borrow(), vec![ids[2], ids[1], ids[0]]);
+    }
+
+    #[test]
+    fn delivery_order_should_survive_leaves_and_joins() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut readers: Vec<_> = (0..4).map(|_| bus.add_rx()).collect();
+        bus.set_priority(readers[3].id(), 1);
+        drop(readers.remove(1));
+        let late = bus.add_rx();
+        bus.set_priority(late.id(), 1);
+        readers.push(late);
+
+        let order = |bus: &Bus<u32>| {
+            let inner = bus.inner.borrow();
+            inner.slots.iter().map(|rx| rx.index).collect::<Vec<_>>()
+        };
+        let ids: Vec<_> = readers.iter().map(|reader| reader.id().0).collect();
+        assert_eq!(order(&bus), vec![ids[2], ids[3], ids[0], ids[1]]);
+    }
+
+    #[test]
+    fn lifecycle_hooks_should_track_subscriptions() {
+        let mut bus = Bus::<Value>::new(5);
+        let log = Rc::new(RefCell::new(vec![]));
+        let subscribed = Rc::clone(&log);
+        bus.on_subscribe(move |sub| subscribed.borrow_mut().push(("+", sub.clone())));
+        let unsubscribed = Rc::clone(&log);
+        bus.on_unsubscribe(move |sub| unsubscribed.borrow_mut().push(("-", sub.clone())));
+
+        let socket = bus.add_rx_labeled("socket");
+        let hud = bus.add_rx();
+        drop(socket);
+        drop(hud);
+
+        let changes: Vec<_> = log
+            .borrow()
+            .iter()
+            .map(|(change, sub)| (*change, sub.reader.0, sub.label.clone(), sub.readers))
+            .collect();
+        let socket = Some("socket".to_string());
+        assert_eq!(
+            changes,
+            vec![
+                ("+", 0, socket.clone(), 1),
+                ("+", 1, None, 2),
+                ("-", 0, socket, 1),
+                ("-", 1, None, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn on_dropped_should_receive_evicted_messages() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx_bounded(1);
+        let _other = bus.add_rx();
+        let dropped = Rc::new(RefCell::new(vec![]));
+        let log = Rc::clone(&dropped);
+        bus.on_dropped(move |reader, msg| log.borrow_mut().push((reader, msg)));
+
+        bus.broadcast(1);
+        bus.broadcast(2);
+        bus.broadcast(3);
+
+        assert_eq!(*dropped.borrow(), vec![(rx.id(), 1), (rx.id(), 2)]);
+        assert_eq!(rx.recv(), vec![3]);
+    }
+
+    #[test]
+    fn broadcast_checked_should_report_slowest_reader() {
+        let mut bus = Bus::<u32>::new(5);
+        assert_eq!(bus.broadcast_checked(0), BroadcastReport::default());
+
+        let mut fast = bus.add_rx();
+        let slow = bus.add_rx_bounded(2);
+
+        bus.broadcast(1);
+        fast.recv();
+        let report = bus.broadcast_checked(2);
+        assert_eq!(report.delivered, 2);
+        assert_eq!(report.dropped, 0);
+        assert_eq!(report.max_queue_len, 2);
+        assert_eq!(report.slowest, Some(slow.id()));
+
+        let report = bus.broadcast_checked(3);
+        assert_eq!(report.dropped, 1);
+        assert_eq!(report.max_queue_len, 2);
+    }
+
+    #[test]
+    fn messages_should_be_stamped_in_order() {
+        let mut bus1 = Bus::<Value>::new(5);
+        let mut bus2 = Bus::<Value>::new(5);
+        let mut rx1 = bus1.add_rx();
+        let mut rx2 = bus2.add_rx();
+
+        bus1.broadcast(Value::A);
+        bus2.broadcast(Value::B);
+        bus1.broadcast(Value::B);
+
+        let stamped1 = rx1.recv_stamped();
+        let stamped2 = rx2.recv_stamped();
+        assert_eq!(stamped1[0].1, Value::A);
+        assert!(stamped1[0].0 < stamped2[0].0);
+        assert!(stamped2[0].0 < stamped1[1].0);
+        assert_eq!(stamped1[1].0, bus2.sequence());
+    }
+
+    #[test]
+    fn recv_newer_than_should_discard_older_messages() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+
+        bus.broadcast(1);
+        bus.broadcast(2);
+        let stamp = bus.sequence();
+        bus.broadcast(3);
+        bus.broadcast(4);
+
+        assert_eq!(rx.recv_newer_than(stamp), vec![3, 4]);
+        assert!(rx.recv().is_empty());
+    }
+
+    #[test]
+    fn recv_for_should_stop_when_budget_elapsed() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        for value in 1..=3 {
+            bus.broadcast(value);
+        }
+
+        let slow = |_: &u32| std::thread::sleep(Duration::from_millis(5));
+        let (done, stopped) = rx.recv_for(Duration::from_millis(1), slow);
+        assert_eq!((done, stopped), (vec![1], true));
+        let (done, stopped) = rx.recv_for(Duration::from_secs(60), |_| {});
+        assert_eq!((done, stopped), (vec![2, 3], false));
+    }
+
+    #[test]
+    fn commit_should_remove_peeked_messages() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        bus.broadcast(1);
+        bus.broadcast(2);
+        bus.broadcast(3);
+
+        assert_eq!(rx.peek_batch(), vec![1, 2, 3]);
+        assert_eq!(rx.commit(1), 1);
+        bus.broadcast(4);
+        assert_eq!(rx.peek_batch(), vec![2, 3, 4]);
+        assert_eq!(rx.commit(5), 3);
+        assert!(rx.recv().is_empty());
+    }
+
+    #[test]
+    fn has_pending_should_follow_the_queue() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        bus.broadcast(1);
+        bus.broadcast(2);
+        let clone = rx.clone();
+        assert!(rx.has_pending() && clone.has_pending());
+
+        assert_eq!(rx.commit(1), 1);
+        assert!(rx.has_pending());
+        bus.retract(|msg| *msg == 2);
+        assert!(!rx.has_pending());
+        assert!(clone.has_pending());
+
+        bus.broadcast(3);
+        rx.recv_ref();
+        assert!(!rx.has_pending());
+    }
+
+    #[test]
+    fn into_pending_should_unsubscribe_and_return_messages() {
+        let mut bus = Bus::<Value>::new(5);
+        let rx = bus.add_rx();
+
+        bus.broadcast(Value::A);
+        bus.broadcast(Value::B);
+
+        assert_eq!(rx.into_pending(), vec![Value::A, Value::B]);
+        assert!(bus.inner.borrow().slots.is_empty());
+    }
+
+    #[test]
+    fn cloned_reader_should_copy_subscription() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx_filtered(|msg| msg % 2 == 1);
+        bus.broadcast(1);
+
+        let mut clone = rx.clone();
+        let mut fork = rx.fork();
+        assert_ne!(clone.id(), rx.id());
+        bus.broadcast(2);
+        bus.broadcast(3);
+
+        assert_eq!(rx.recv(), vec![1, 3]);
+        assert_eq!(clone.recv(), vec![1, 3]);
+        assert_eq!(fork.recv(), vec![3]);
+        drop(rx);
+        bus.broadcast(5);
+        assert_eq!(clone.recv(), vec![5]);
+    }
+
+    #[test]
+    fn reader_should_use_custom_storage() {
+        // ring buffer keeping the 2 first messages
+        struct FirstTwo(VecDeque<u32>);
+        impl QueueStorage<u32> for FirstTwo {
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+            fn push_back(&mut self, value: u32) {
+                self.0.push_back(value);
+            }
+            fn pop_front(&mut self) -> Option<u32> {
+                self.0.pop_front()
+            }
+            fn limit(&self) -> Option<usize> {
+                Some(2)
+            }
+            fn overflow(&self) -> Overflow {
+                Overflow::DropNewest
+            }
+        }
+
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx_with(FirstTwo(VecDeque::new()));
+
+        bus.broadcast(1);
+        bus.broadcast(2);
+        let report = bus.broadcast_checked(3);
+
+        assert_eq!(report.dropped, 1);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.recv_stamped().len(), 1);
+    }
+
+    #[test]
+    fn poll_each_should_process_pending_messages() {
+        let bus = Rc::new(RefCell::new(Bus::<u32>::new(5)));
+        let mut rx = bus.borrow_mut().add_rx();
+
+        bus.borrow().broadcast(1);
+        bus.borrow().broadcast(2);
+
+        let mut seen = vec![];
+        let count = rx.poll_each(|msg| {
+            seen.push(msg);
+            bus.borrow().broadcast(msg * 10);
+        });
+
+        assert_eq!(count, 2);
+        assert_eq!(seen, vec![1, 2]);
+        assert_eq!(rx.recv(), vec![10, 20]);
+        assert_eq!(rx.poll_each(|_| {}), 0);
+    }
+
+    #[test]
+    fn retract_should_remove_queued_messages() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx1 = bus.add_rx();
+        let mut rx2 = bus.add_rx();
+        bus.broadcast(1);
+        bus.broadcast(2);
+        let stamp = bus.sequence();
+        bus.broadcast(3);
+        assert_eq!(rx1.recv(), vec![1, 2, 3]);
+
+        assert_eq!(bus.retract(|msg| *msg == 1), 1);
+        assert_eq!(bus.retract_stamp(stamp), 1);
+        assert_eq!(rx2.recv_stamped(), vec![(stamp + 1, 3)]);
+    }
+
+    #[test]
+    fn send_to_should_reach_one_reader() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx1 = bus.add_rx();
+        let mut rx2 = bus.add_rx_filtered(|_| false);
+        let id = rx2.id();
+
+        assert_eq!(bus.send_to(id, 1), Ok(()));
+        assert!(rx1.recv().is_empty());
+        assert_eq!(rx2.recv(), vec![1]);
+        drop(rx2);
+        assert_eq!(bus.send_to(id, 2), Err(SendError(2)));
+    }
+
+    #[test]
+    fn replies_should_reach_producer() {
+        let mut bus = Bus::<u32>::new(5);
+        let rx1 = bus.add_rx();
+        let rx2 = bus.add_rx();
+
+        rx2.reply(2);
+        rx1.reply(1);
+        drop(rx2);
+        assert_eq!(bus.replies(), vec![(ReaderId(1), 2), (rx1.id(), 1)]);
+        assert!(bus.replies().is_empty());
+    }
+
+    #[test]
+    fn broadcast_if_changed_should_skip_repeated_values() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx = bus.add_rx();
+
+        assert!(bus.broadcast_if_changed(Value::A));
+        assert!(!bus.broadcast_if_changed(Value::A));
+        bus.broadcast(Value::B);
+        assert!(!bus.broadcast_if_changed(Value::A));
+        assert!(bus.broadcast_if_changed(Value::B));
+
+        assert_eq!(rx.recv(), vec![Value::A, Value::B, Value::B]);
+    }
+
+    #[test]
+    fn broadcast_if_changed_should_forget_intercepted_values() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx = bus.add_rx();
+        let mut paused = true;
+        bus.add_interceptor(move |msg| match std::mem::take(&mut paused) {
+            true => Intercept::Drop,
+            false => Intercept::Forward(msg),
+        });
+
+        assert!(!bus.broadcast_if_changed(Value::A));
+        assert!(bus.broadcast_if_changed(Value::A));
+        assert!(!bus.broadcast_if_changed(Value::A));
+        assert_eq!(rx.recv(), vec![Value::A]);
+    }
+
+    #[test]
+    fn shared_bus_should_share_payload() {
+        let mut bus = SharedBus::<Vec<u8>>::new(5);
+        let mut rx1 = bus.add_rx();
+        let mut rx2 = bus.add_rx();
+
+        bus.broadcast_shared(vec![1, 2, 3]);
+
+        let msg1 = rx1.recv().pop().unwrap();
+        let msg2 = rx2.recv().pop().unwrap();
+        assert!(Rc::ptr_eq(&msg1, &msg2));
+        assert_eq!(Rc::strong_count(&msg1), 2);
+        assert_eq!(*msg1, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn recv_pooled_should_reuse_buffers() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx = bus.add_rx();
+
+        bus.broadcast(Value::A);
+        bus.broadcast(Value::B);
+
+        let batch = rx.recv_pooled();
+        assert_eq!(&batch[..], &[Value::A, Value::B]);
+        let ptr = batch.as_ptr();
+        drop(batch);
+        assert_eq!(bus.inner.borrow().pool.len(), 1);
+
+        bus.broadcast(Value::B);
+        let batch = rx.recv_pooled();
+        assert_eq!(&batch[..], &[Value::B]);
+        assert_eq!(batch.as_ptr(), ptr);
+        assert!(bus.inner.borrow().pool.is_empty());
+    }
+
+    #[test]
+    fn recv_ref_should_borrow_and_clear_queue() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        let mut custom = bus.add_rx_with(VecDeque::new());
+        for value in 1..=3 {
+            bus.broadcast(value);
+        }
+        rx.recv_one();
+        bus.broadcast(4);
+
+        assert_eq!(*rx.recv_ref(), [2, 3, 4]);
+        assert_eq!(rx.pending(), 0);
+        assert_eq!(*custom.recv_ref(), [1, 2, 3, 4]);
+        assert_eq!(custom.pending(), 0);
+    }
+
+    #[test]
+    fn subscriptions_should_be_deferred_while_bus_is_borrowed() {
+        let mut bus = Bus::<u32>::new(5);
+        let log = Rc::new(RefCell::new(vec![]));
+        let subscribed = Rc::clone(&log);
+        bus.on_subscribe(move |sub| subscribed.borrow_mut().push(sub.reader.0));
+        let mut rx = bus.add_rx();
+        let hud = bus.add_rx();
+        bus.broadcast(1);
+
+        let new = {
+            let pending = rx.recv_ref();
+            let new = bus.add_rx_labeled("new");
+            drop(hud);
+            assert_eq!(*pending, [1]);
+            assert_eq!(*log.borrow(), vec![0, 1]);
+            new
+        };
+        assert_eq!(*log.borrow(), vec![0, 1, 2]);
+        assert_eq!(bus.inspect().readers.len(), 2);
+        assert_eq!(new.label(), Some("new".to_string()));
+
+        bus.broadcast(2);
+        assert_eq!(new.pending(), 1);
+    }
+
+    #[test]
+    fn forks_should_be_deferred_while_bus_is_borrowed() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        let other = bus.add_rx();
+        bus.broadcast(1);
+
+        let (mut fork, mut clone) = {
+            let pending = rx.recv_ref();
+            let forks = (other.fork(), other.clone());
+            assert_eq!(*pending, [1]);
+            forks
+        };
+        assert_eq!(bus.inspect().readers.len(), 4);
+
+        bus.broadcast(2);
+        assert_eq!(fork.recv(), vec![2]);
+        assert_eq!(clone.recv(), vec![1, 2]);
+    }
+
+    #[test]
+    fn readers_dropped_with_messages_should_unsubscribe() {
+        // messages can carry readers, dropped while the bus is borrowed
+        #[derive(Clone)]
+        struct Message(Rc<BusReader<Message>>);
+
+        let mut bus = Bus::<Message>::new(5);
+        let rx = bus.add_rx();
+        let carried = bus.add_rx();
+        let id = carried.id();
+        bus.broadcast(Message(Rc::new(carried)));
+        assert_eq!(bus.inspect().readers.len(), 2);
+
+        assert_eq!(bus.retract(|msg| msg.0.id() == id), 2);
+        assert_eq!(bus.inspect().readers.len(), 1);
+        drop(rx);
+        assert!(bus.inspect().readers.is_empty());
+    }
+
+    #[test]
+    fn batch_should_outlive_bus() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx = bus.add_rx();
+
+        bus.broadcast(Value::A);
+        let batch = rx.recv_pooled();
+
+        drop(rx);
+        drop(bus);
+        assert_eq!(batch.len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "smallvec")]
+    fn queue_should_store_few_messages_inline() {
+        let mut bus = Bus::<Value>::new(5);
+        let mut rx = bus.add_rx();
+
+        for _ in 0..INLINE_MESSAGES {
+            bus.broadcast(Value::A);
+        }
+        let spilled = |bus: &Bus<Value>| match &bus.inner.borrow().slot(0).unwrap().queue {
+            Storage::Default(queue) => queue.spilled(),
+            Storage::Custom(_) => unreachable!(),
+        };
+        assert!(!spilled(&bus));
+
+        bus.broadcast(Value::B);
+        assert!(spilled(&bus));
+        assert_eq!(rx.recv().len(), INLINE_MESSAGES + 1);
     }
 
     #[test]
@@ -0,0 +1,158 @@
+//! Readers converting the messages they receive.
+
+use std::convert::TryFrom;
+
+use crate::{Bus, BusReader, ReaderId, Receiver, TryRecvError, Variant};
+
+/// `MapReader` wraps a `BusReader`, converting its messages and skipping
+/// the ones which don't convert.
+pub struct MapReader<T: Clone, U> {
+    reader: BusReader<T>,
+    map: fn(T) -> Option<U>,
+}
+impl<T: Clone, U> MapReader<T, U> {
+    /// Wrap `reader`, converting its messages with `map`
+    pub fn new(reader: BusReader<T>, map: fn(T) -> Option<U>) -> MapReader<T, U> {
+        MapReader::<T, U> { reader, map }
+    }
+
+    /// The identifier of the reader on its bus
+    pub fn id(&self) -> ReaderId {
+        self.reader.id()
+    }
+
+    /// Receive the pending messages which convert (if any) and empty the queue
+    pub fn recv(&mut self) -> Vec<U> {
+        let map = self.map;
+        self.reader.recv().into_iter().filter_map(map).collect()
+    }
+
+    /// Receive the next pending message which converts, or tell whether the queue is
+    /// just empty or the `Bus` was dropped.
+    pub fn try_recv(&mut self) -> Result<U, TryRecvError> {
+        loop {
+            if let Some(value) = (self.map)(self.reader.try_recv()?) {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Whether the `Bus` was dropped; pending messages can still be received.
+    pub fn is_closed(&self) -> bool {
+        self.reader.is_closed()
+    }
+
+    /// Release the underlying `BusReader`.
+    pub fn into_inner(self) -> BusReader<T> {
+        self.reader
+    }
+}
+
+impl<T: Clone, U> Receiver<U> for MapReader<T, U> {
+    fn try_recv(&mut self) -> Result<U, TryRecvError> {
+        MapReader::try_recv(self)
+    }
+
+    fn is_closed(&self) -> bool {
+        MapReader::is_closed(self)
+    }
+
+    fn recv(&mut self) -> Vec<U> {
+        MapReader::recv(self)
+    }
+}
+
+impl<T: Clone> BusReader<T> {
+    /// Turn the reader into a reader of `U`, converting the messages with `From`.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u8>::new(10);
+    /// let mut reader = bus.add_rx().convert::<u32>();
+    /// bus.broadcast(1);
+    /// assert_eq!(reader.recv(), vec![1u32]);
+    /// ```
+    pub fn convert<U: From<T>>(self) -> MapReader<T, U> {
+        MapReader::new(self, |value| Some(U::from(value)))
+    }
+
+    /// Turn the reader into a reader of `U`, converting the messages with `TryFrom`;
+    /// the messages which fail to convert are skipped.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<i32>::new(10);
+    /// let mut reader = bus.add_rx().try_convert::<u8>();
+    /// bus.broadcast(-1);
+    /// bus.broadcast(1);
+    /// assert_eq!(reader.recv(), vec![1u8]);
+    /// ```
+    pub fn try_convert<U: TryFrom<T>>(self) -> MapReader<T, U> {
+        MapReader::new(self, |value| U::try_from(value).ok())
+    }
+}
+
+impl<T: Clone + 'static> Bus<T> {
+    /// Create a new reader of the `V` variant of the event enum: only the events of this variant
+    /// are queued, and it receives their payload (see `BusEvent`).
+    /// ```ignore
+    /// #[derive(Clone, BusEvent)]
+    /// enum Event { Resize(Resize), Key(Key) }
+    ///
+    /// let mut resizes = bus.add_rx_variant::<Resize>();
+    /// ```
+    pub fn add_rx_variant<V: Variant<T> + 'static>(&mut self) -> MapReader<T, V> {
+        MapReader::new(self.add_rx_filtered(V::matches), V::from_event)
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    enum Event {
+        Count(u32),
+        Flag(bool),
+    }
+    impl Variant<Event> for u32 {
+        fn matches(event: &Event) -> bool {
+            matches!(event, Event::Count(_))
+        }
+
+        fn from_event(event: Event) -> Option<u32> {
+            match event {
+                Event::Count(count) => Some(count),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn variant_reader_should_only_queue_its_variant() {
+        let mut bus = Bus::<Event>::new(5);
+        let mut counts = bus.add_rx_variant::<u32>();
+
+        bus.broadcast(Event::Count(1));
+        bus.broadcast(Event::Flag(true));
+        bus.broadcast(Event::Count(2));
+
+        assert_eq!(bus.inspect().readers[0].queue_len, 2);
+        assert_eq!(counts.try_recv(), Ok(1));
+        assert_eq!(counts.recv(), vec![2]);
+    }
+
+    #[test]
+    fn converted_reader_should_skip_failures() {
+        let mut bus = Bus::<i64>::new(5);
+        let mut wide = bus.add_rx().convert::<i128>();
+        let mut narrow = bus.add_rx().try_convert::<u8>();
+
+        bus.broadcast(1);
+        bus.broadcast(-1);
+        bus.broadcast(300);
+        bus.broadcast(2);
+
+        assert_eq!(wide.recv(), vec![1, -1, 300, 2]);
+        assert_eq!(narrow.try_recv(), Ok(1));
+        assert_eq!(narrow.recv(), vec![2]);
+    }
+}
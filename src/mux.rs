@@ -0,0 +1,166 @@
+/*!
+Multiplexing several buses.
+
+A `Mux<E>` gathers buses of different message types, and hands out `MuxReader`s receiving
+the messages of all of them, converted into `E` with `From`, in broadcast order.
+
+```rust
+use syncbus::{mux::Mux, Bus};
+
+#[derive(Debug, PartialEq)]
+enum Event { Key(char), Net(u32) }
+impl From<char> for Event {
+    fn from(key: char) -> Event { Event::Key(key) }
+}
+impl From<u32> for Event {
+    fn from(packet: u32) -> Event { Event::Net(packet) }
+}
+
+let input = Bus::<char>::new(10);
+let net = Bus::<u32>::new(10);
+let mut mux = Mux::<Event>::new();
+mux.add(&input);
+mux.add(&net);
+let mut reader = mux.add_rx();
+
+net.broadcast(1);
+input.broadcast('a');
+assert_eq!(reader.recv(), vec![Event::Net(1), Event::Key('a')]);
+```
+*/
+
+use std::rc::Rc;
+
+use crate::{Bus, BusInner, BusReader, Shared};
+
+// A reader of one of the buses, converting its messages
+trait Source<E> {
+    fn drain_into(&mut self, out: &mut Vec<(u64, E)>);
+    fn is_closed(&self) -> bool;
+}
+impl<T: Clone, E: From<T>> Source<E> for BusReader<T> {
+    fn drain_into(&mut self, out: &mut Vec<(u64, E)>) {
+        let messages = self.recv_stamped().into_iter();
+        out.extend(messages.map(|(stamp, value)| (stamp, E::from(value))));
+    }
+
+    fn is_closed(&self) -> bool {
+        BusReader::is_closed(self)
+    }
+}
+
+type Subscribe<E> = Box<dyn Fn() -> Box<dyn Source<E>>>;
+
+/// `Mux` combines buses of different message types; use `add_rx()` to read them all.
+pub struct Mux<E> {
+    buses: Vec<Subscribe<E>>,
+}
+impl<E: 'static> Mux<E> {
+    /// Create a `Mux` without buses
+    pub fn new() -> Mux<E> {
+        Mux::<E> { buses: vec![] }
+    }
+
+    /// Add a bus; the readers created afterwards will receive its messages.
+    pub fn add<T: Clone + 'static>(&mut self, bus: &Bus<T>)
+    where
+        E: From<T>,
+    {
+        let inner: Rc<Shared<T>> = Rc::clone(&bus.inner);
+        self.buses.push(Box::new(move || {
+            Box::new(BusInner::subscribe(&inner, |_| {}))
+        }));
+    }
+
+    /// Create a new `MuxReader`, subscribed to all the buses added so far.
+    pub fn add_rx(&self) -> MuxReader<E> {
+        MuxReader::<E> {
+            sources: self.buses.iter().map(|subscribe| subscribe()).collect(),
+        }
+    }
+}
+impl<E: 'static> Default for Mux<E> {
+    fn default() -> Mux<E> {
+        Mux::new()
+    }
+}
+
+/// `MuxReader` receives the messages of the buses of a `Mux`.
+pub struct MuxReader<E> {
+    sources: Vec<Box<dyn Source<E>>>,
+}
+impl<E> MuxReader<E> {
+    /// Receive the pending messages of all the buses (if any), ordered by broadcast sequence.
+    pub fn recv(&mut self) -> Vec<E> {
+        let mut stamped = vec![];
+        for source in self.sources.iter_mut() {
+            source.drain_into(&mut stamped);
+        }
+        stamped.sort_by_key(|(stamp, _)| *stamp);
+        stamped.into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// Whether all the buses were dropped; pending messages can still be received.
+    pub fn is_closed(&self) -> bool {
+        self.sources.iter().all(|source| source.is_closed())
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    enum Value {
+        A,
+        B,
+    }
+
+    #[derive(PartialEq, Debug)]
+    enum Tagged {
+        Value(Value),
+        Count(u32),
+    }
+    impl From<Value> for Tagged {
+        fn from(value: Value) -> Tagged {
+            Tagged::Value(value)
+        }
+    }
+    impl From<u32> for Tagged {
+        fn from(count: u32) -> Tagged {
+            Tagged::Count(count)
+        }
+    }
+
+    #[test]
+    fn mux_reader_should_merge_buses_in_order() {
+        let values = Bus::<Value>::new(5);
+        let counts = Bus::<u32>::new(5);
+        let mut mux = Mux::<Tagged>::new();
+        mux.add(&values);
+        mux.add(&counts);
+        let mut rx = mux.add_rx();
+
+        values.broadcast(Value::A);
+        counts.broadcast(1);
+        values.broadcast(Value::B);
+        counts.broadcast(2);
+
+        assert_eq!(
+            rx.recv(),
+            vec![
+                Tagged::Value(Value::A),
+                Tagged::Count(1),
+                Tagged::Value(Value::B),
+                Tagged::Count(2),
+            ]
+        );
+
+        drop(values);
+        assert!(!rx.is_closed());
+        drop(counts);
+        assert!(rx.is_closed());
+    }
+}
@@ -0,0 +1,142 @@
+/*!
+Pull-style queries.
+
+A `QueryBus<Q, A>` asks a question to all its handlers and collects their answers:
+handlers are registered with `add_handler()`, and `query()` calls them in registration order.
+
+```rust
+use syncbus::query::QueryBus;
+
+let mut tools = QueryBus::<(i32, i32), &str>::new();
+tools.add_handler(|point| if point.0 < 100 { Some("toolbar") } else { None });
+tools.add_handler(|_| Some("canvas"));
+
+assert_eq!(tools.query(&(10, 10)), vec!["toolbar", "canvas"]);
+assert_eq!(tools.query(&(200, 10)), vec!["canvas"]);
+```
+*/
+
+use std::cell::RefCell;
+
+/// `HandlerId` identifies a handler on its `QueryBus`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct HandlerId(pub usize);
+
+type QueryHandler<Q, A> = Box<dyn FnMut(&Q) -> Option<A>>;
+// A handler and its index; the handler is taken out while it runs
+type Slot<Q, A> = (usize, Option<QueryHandler<Q, A>>);
+
+/// `QueryBus` calls its handlers with a query, and collects their answers.
+pub struct QueryBus<Q, A> {
+    handlers: RefCell<Vec<Slot<Q, A>>>,
+    count: usize,
+}
+impl<Q, A> QueryBus<Q, A> {
+    /// Create a `QueryBus` without handlers
+    pub fn new() -> QueryBus<Q, A> {
+        QueryBus::<Q, A> {
+            handlers: RefCell::new(vec![]),
+            count: 0,
+        }
+    }
+
+    /// Register a handler: it answers the queries with `Some(answer)`, or ignores them with `None`.
+    pub fn add_handler<F>(&mut self, handler: F) -> HandlerId
+    where
+        F: FnMut(&Q) -> Option<A> + 'static,
+    {
+        let index = self.count;
+        self.count += 1;
+        let handler: QueryHandler<Q, A> = Box::new(handler);
+        self.handlers.borrow_mut().push((index, Some(handler)));
+        HandlerId(index)
+    }
+
+    /// Unregister a handler
+    pub fn remove_handler(&mut self, id: HandlerId) {
+        self.handlers
+            .borrow_mut()
+            .retain(|(index, _)| *index != id.0);
+    }
+
+    /// Number of registered handlers
+    pub fn handlers(&self) -> usize {
+        self.handlers.borrow().len()
+    }
+
+    /// Call the handlers with the query, in registration order, and return their answers.
+    /// The bus isn't borrowed while handlers run: a handler querying the bus again
+    /// only gets the answers of the other handlers.
+    pub fn query(&self, query: &Q) -> Vec<A> {
+        let ids: Vec<usize> = self.handlers.borrow().iter().map(|(id, _)| *id).collect();
+        let mut answers = vec![];
+        for id in ids {
+            let handler = {
+                let mut handlers = self.handlers.borrow_mut();
+                let handler = handlers.iter_mut().find(|(index, _)| *index == id);
+                handler.and_then(|(_, handler)| handler.take())
+            };
+            // removed, or running a nested query
+            let mut handler = match handler {
+                Some(handler) => handler,
+                None => continue,
+            };
+            answers.extend(handler(query));
+            let mut handlers = self.handlers.borrow_mut();
+            if let Some((_, slot)) = handlers.iter_mut().find(|(index, _)| *index == id) {
+                *slot = Some(handler);
+            }
+        }
+        answers
+    }
+}
+impl<Q, A> Default for QueryBus<Q, A> {
+    fn default() -> QueryBus<Q, A> {
+        QueryBus::new()
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::rc::{Rc, Weak};
+
+    #[test]
+    fn query_should_collect_answers_in_order() {
+        let mut bus = QueryBus::<u32, String>::new();
+        let low = bus.add_handler(|n| Some(format!("low {}", n)));
+        bus.add_handler(|n| {
+            if n % 2 == 0 {
+                Some("even".to_string())
+            } else {
+                None
+            }
+        });
+        bus.add_handler(|_| Some("any".to_string()));
+
+        assert_eq!(bus.query(&2), vec!["low 2", "even", "any"]);
+        assert_eq!(bus.query(&3), vec!["low 3", "any"]);
+
+        bus.remove_handler(low);
+        assert_eq!(bus.handlers(), 2);
+        assert_eq!(bus.query(&3), vec!["any"]);
+    }
+
+    #[test]
+    fn nested_query_should_skip_running_handler() {
+        let shared = Rc::new(RefCell::new(Weak::<QueryBus<u32, u32>>::new()));
+        let mut bus = QueryBus::<u32, u32>::new();
+        let outer = Rc::clone(&shared);
+        bus.add_handler(move |n| {
+            let bus = outer.borrow().upgrade()?;
+            Some(bus.query(&(n + 1)).iter().sum())
+        });
+        bus.add_handler(|n| Some(*n));
+        let bus = Rc::new(bus);
+        *shared.borrow_mut() = Rc::downgrade(&bus);
+
+        assert_eq!(bus.query(&1), vec![2, 1]);
+    }
+}
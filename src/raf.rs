@@ -0,0 +1,35 @@
+// requestAnimationFrame loop shared by the browser integrations
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gloo_render::{request_animation_frame, AnimationFrame};
+
+// Calls `callback` on every animation frame, until dropped.
+pub(crate) struct RafLoop {
+    frame: Rc<RefCell<Option<AnimationFrame>>>,
+}
+impl RafLoop {
+    pub(crate) fn start(callback: impl FnMut() + 'static) -> RafLoop {
+        let frame = Rc::new(RefCell::new(None));
+        schedule(&frame, Rc::new(RefCell::new(callback)));
+        RafLoop { frame }
+    }
+}
+impl Drop for RafLoop {
+    fn drop(&mut self) {
+        // dropping the pending `AnimationFrame` cancels it
+        self.frame.borrow_mut().take();
+    }
+}
+
+fn schedule(frame: &Rc<RefCell<Option<AnimationFrame>>>, callback: Rc<RefCell<dyn FnMut()>>) {
+    let weak = Rc::downgrade(frame);
+    let next = request_animation_frame(move |_| {
+        if let Some(frame) = weak.upgrade() {
+            (callback.borrow_mut())();
+            schedule(&frame, callback);
+        }
+    });
+    *frame.borrow_mut() = Some(next);
+}
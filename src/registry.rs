@@ -0,0 +1,84 @@
+/*!
+Thread-local registry of buses, one per message type.
+
+Deeply nested components can reach a bus without threading its handle through their
+constructors: `registry::bus::<T>()` returns the bus of `T` for the current thread,
+created on first use.
+
+```rust
+use syncbus::registry;
+
+#[derive(Clone, Debug, PartialEq)]
+struct Resized(u32, u32);
+
+let mut reader = registry::subscribe::<Resized>();
+registry::bus::<Resized>().borrow().broadcast(Resized(800, 600));
+assert_eq!(reader.recv(), vec![Resized(800, 600)]);
+```
+*/
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{Bus, BusReader, MIN_CAPACITY};
+
+thread_local! {
+    static BUSES: RefCell<HashMap<TypeId, Box<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// The bus of `T` for the current thread, created on first use.
+pub fn bus<T: Clone + 'static>() -> Rc<RefCell<Bus<T>>> {
+    BUSES.with(|buses| {
+        let mut buses = buses.borrow_mut();
+        let bus = buses
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Rc::new(RefCell::new(Bus::<T>::new(MIN_CAPACITY)))));
+        match bus.downcast_ref::<Rc<RefCell<Bus<T>>>>() {
+            Some(bus) => Rc::clone(bus),
+            None => unreachable!("registry entries are keyed by their type"),
+        }
+    })
+}
+
+/// Create a new reader of the bus of `T`, see `Bus::add_rx()`.
+pub fn subscribe<T: Clone + 'static>() -> BusReader<T> {
+    bus::<T>().borrow_mut().add_rx()
+}
+
+/// Broadcast the value on the bus of `T`, see `Bus::broadcast()`.
+pub fn broadcast<T: Clone + 'static>(value: T) {
+    bus::<T>().borrow().broadcast(value);
+}
+
+/// Remove the bus of `T` from the registry: it is closed once the handles returned by `bus()`
+/// are dropped, and the next call to `bus()` creates a new one.
+pub fn remove<T: Clone + 'static>() {
+    let bus = BUSES.with(|buses| buses.borrow_mut().remove(&TypeId::of::<T>()));
+    drop(bus);
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn registry_should_keep_a_bus_per_type() {
+        let mut numbers = subscribe::<u32>();
+        let mut words = subscribe::<&str>();
+
+        broadcast(1u32);
+        broadcast("one");
+        assert!(Rc::ptr_eq(&bus::<u32>(), &bus::<u32>()));
+        assert_eq!(numbers.recv(), vec![1]);
+        assert_eq!(words.recv(), vec!["one"]);
+
+        remove::<u32>();
+        assert!(numbers.is_closed());
+        broadcast(2u32);
+        assert!(numbers.recv().is_empty());
+    }
+}
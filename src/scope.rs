@@ -0,0 +1,105 @@
+//! Subscriptions tied to a scope, or to the lifetime of an owner.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::{Bus, BusInner, BusReader};
+
+/// `OwnerToken` unsubscribes the readers created with `Bus::add_rx_for()` when it is dropped,
+/// even if the readers themselves were leaked, e.g. in a callback.
+#[derive(Default)]
+pub struct OwnerToken {
+    releases: RefCell<Vec<Box<dyn FnOnce()>>>,
+}
+impl OwnerToken {
+    /// Create a token without readers
+    pub fn new() -> OwnerToken {
+        OwnerToken::default()
+    }
+}
+impl Drop for OwnerToken {
+    fn drop(&mut self) {
+        for release in self.releases.get_mut().drain(..) {
+            release();
+        }
+    }
+}
+
+impl<T: Clone> Bus<T> {
+    /// Create a reader for the duration of `scope`: it is unsubscribed when `scope` returns
+    /// or panics.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// let pending = bus.with_subscription(|reader| reader.pending());
+    /// # assert_eq!(pending, 0);
+    /// assert!(bus.inspect().readers.is_empty());
+    /// ```
+    pub fn with_subscription<R, F>(&mut self, scope: F) -> R
+    where
+        F: FnOnce(&mut BusReader<T>) -> R,
+    {
+        let mut reader = self.add_rx();
+        scope(&mut reader)
+    }
+}
+
+impl<T: Clone + 'static> Bus<T> {
+    /// Create a new reader, which is unsubscribed when the `owner` is dropped;
+    /// it then stops receiving messages.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// use syncbus::OwnerToken;
+    ///
+    /// let owner = OwnerToken::new();
+    /// let mut reader = bus.add_rx_for(&owner);
+    /// drop(owner);
+    /// bus.broadcast(1);
+    /// assert!(reader.recv().is_empty());
+    /// ```
+    pub fn add_rx_for(&mut self, owner: &OwnerToken) -> BusReader<T> {
+        let reader = self.add_rx();
+        let (shared, index) = (Rc::downgrade(&reader.inner), reader.index);
+        owner.releases.borrow_mut().push(Box::new(move || {
+            if let Some(shared) = shared.upgrade() {
+                BusInner::detach(&shared, index);
+            }
+        }));
+        reader
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn scoped_reader_should_unsubscribe_on_panic() {
+        let mut bus = Bus::<u32>::new(5);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            bus.with_subscription(|_| panic!("failed"));
+        }));
+        assert!(result.is_err());
+        assert!(bus.inspect().readers.is_empty());
+    }
+
+    #[test]
+    fn owner_token_should_unsubscribe_readers() {
+        let mut bus = Bus::<u32>::new(5);
+        let owner = OwnerToken::new();
+        let mut rx1 = bus.add_rx_for(&owner);
+        let rx2 = bus.add_rx_for(&owner);
+        let _other = bus.add_rx();
+        bus.broadcast(1);
+        assert_eq!(rx1.recv(), vec![1]);
+
+        drop(owner);
+        assert_eq!(bus.inspect().readers.len(), 1);
+        bus.broadcast(2);
+        assert!(rx1.recv().is_empty());
+        drop(rx2);
+        assert_eq!(bus.inspect().readers.len(), 1);
+    }
+}
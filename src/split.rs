@@ -0,0 +1,153 @@
+//! The two halves of a `Bus`, see `Bus::split()`.
+
+use std::rc::Rc;
+
+use crate::{
+    BroadcastReport, Broadcaster, BusInner, BusReader, QueueStorage, ReaderId, SendError, Shared,
+    Storage, SEQUENCE,
+};
+
+/// `BusSender` is the producer half of a split `Bus`: it can only broadcast.
+/// The bus is closed when it is dropped.
+pub struct BusSender<T: Clone> {
+    pub(crate) inner: Rc<Shared<T>>,
+}
+impl<T: Clone> Drop for BusSender<T> {
+    fn drop(&mut self) {
+        BusInner::release_sender(&self.inner);
+    }
+}
+impl<T: Clone> BusSender<T> {
+    /// Push copies of the value in the reader queues.
+    pub fn broadcast(&self, value: T) {
+        BusInner::publish(&self.inner, value, None);
+    }
+
+    /// Push copies of the value in the reader queues, and report on the state of the queues,
+    /// see `Bus::broadcast_checked()`.
+    pub fn broadcast_checked(&self, value: T) -> BroadcastReport {
+        let mut report = BusInner::publish(&self.inner, value, None);
+        self.inner.borrow().measure(&mut report);
+        report
+    }
+
+    /// Push copies of the value in the reader queues; fails, giving the value back,
+    /// if there are no readers.
+    pub fn try_broadcast(&self, value: T) -> Result<(), SendError<T>> {
+        if self.inner.borrow().slots.is_empty() {
+            return Err(SendError(value));
+        }
+        self.broadcast(value);
+        Ok(())
+    }
+
+    /// Receive the messages sent back by the readers, see `Bus::replies()`.
+    pub fn replies(&self) -> Vec<(ReaderId, T)> {
+        std::mem::take(&mut self.inner.borrow_mut().replies)
+    }
+
+    /// Push the value in the queue of the `reader` only, see `Bus::send_to()`.
+    pub fn send_to(&self, reader: ReaderId, value: T) -> Result<(), SendError<T>> {
+        BusInner::send_to(&self.inner, reader.0, value).map_err(SendError)
+    }
+
+    /// Broadcast the value on `topic`, see `Bus::broadcast_topic()`.
+    pub fn broadcast_topic(&self, topic: &str, value: T) {
+        BusInner::publish_on(&self.inner, Some(topic), value, None);
+    }
+
+    /// The stamp of the latest message broadcast, see `Bus::sequence()`.
+    pub fn sequence(&self) -> u64 {
+        SEQUENCE.with(|sequence| sequence.get())
+    }
+}
+
+impl<T: Clone> Broadcaster<T> for BusSender<T> {
+    fn broadcast(&self, value: T) {
+        BusSender::broadcast(self, value);
+    }
+
+    fn try_broadcast(&self, value: T) -> Result<(), SendError<T>> {
+        BusSender::try_broadcast(self, value)
+    }
+}
+
+/// `BusSubscriber` is the consumer half of a split `Bus`: it can only add readers.
+pub struct BusSubscriber<T: Clone> {
+    pub(crate) inner: Rc<Shared<T>>,
+}
+impl<T: Clone> Clone for BusSubscriber<T> {
+    fn clone(&self) -> BusSubscriber<T> {
+        BusSubscriber::<T> {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+impl<T: Clone> BusSubscriber<T> {
+    /// Create a new `BusReader`, see `Bus::add_rx()`.
+    pub fn add_rx(&mut self) -> BusReader<T> {
+        BusInner::subscribe(&self.inner, |_| {})
+    }
+
+    /// Create a new `BusReader` with a custom storage, see `Bus::add_rx_with()`.
+    pub fn add_rx_with<S>(&mut self, storage: S) -> BusReader<T>
+    where
+        S: QueueStorage<T> + 'static,
+    {
+        BusInner::subscribe(&self.inner, |rx| {
+            rx.queue = Storage::Custom(Box::new(storage))
+        })
+    }
+
+    /// Create a new bounded `BusReader`, see `Bus::add_rx_bounded()`.
+    pub fn add_rx_bounded(&mut self, max_len: usize) -> BusReader<T> {
+        BusInner::subscribe(&self.inner, |rx| rx.max_len = Some(max_len.max(1)))
+    }
+
+    /// Create a new labelled `BusReader`, see `Bus::add_rx_labeled()`.
+    pub fn add_rx_labeled(&mut self, label: &str) -> BusReader<T> {
+        BusInner::subscribe(&self.inner, |rx| rx.label = Some(label.to_string()))
+    }
+
+    /// Create a new filtered `BusReader`, see `Bus::add_rx_filtered()`.
+    pub fn add_rx_filtered<F>(&mut self, filter: F) -> BusReader<T>
+    where
+        F: Fn(&T) -> bool + 'static,
+    {
+        BusInner::subscribe(&self.inner, |rx| rx.filter = Some(Rc::new(filter)))
+    }
+
+    /// Create a new `BusReader` joining a group, see `Bus::add_rx_group()`.
+    pub fn add_rx_group(&mut self, group: &str) -> BusReader<T> {
+        BusInner::subscribe_group(&self.inner, group)
+    }
+
+    /// Create a new `BusReader` of a topic filter, see `Bus::add_rx_topic()`.
+    pub fn add_rx_topic(&mut self, filter: &str) -> BusReader<T> {
+        BusInner::subscribe_topic(&self.inner, filter)
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use crate::Bus;
+
+    #[test]
+    fn split_halves_should_share_the_bus() {
+        let bus = Bus::<u32>::new(5);
+        let (sender, mut subscriber) = bus.split();
+        let mut rx1 = subscriber.add_rx();
+        let mut rx2 = subscriber.clone().add_rx_bounded(1);
+
+        sender.broadcast(1);
+        sender.broadcast(2);
+        assert_eq!(rx1.recv(), vec![1, 2]);
+        assert_eq!(rx2.recv(), vec![2]);
+        assert!(!rx1.is_closed());
+
+        drop(sender);
+        assert!(rx1.is_closed());
+    }
+}
@@ -0,0 +1,265 @@
+//! Pluggable storage of the reader queues.
+
+use std::collections::VecDeque;
+
+/// What happens when a message is pushed in a full queue.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Overflow {
+    /// Drop the oldest message to make room
+    DropOldest,
+    /// Drop the new message
+    DropNewest,
+}
+
+/// Cap on the messages buffered across all the reader queues of a bus, see `Bus::set_budget()`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Budget {
+    /// Number of messages
+    Messages(usize),
+    /// Approximate size of the messages, counted as `size_of::<T>()` each
+    /// (memory owned by the messages, like `String` contents, isn't counted)
+    Bytes(usize),
+}
+
+/// Which message is evicted when the `Budget` is exceeded.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Eviction {
+    /// The oldest message of the longest queue
+    LongestQueue,
+    /// The oldest message of all the queues
+    OldestMessage,
+}
+
+/// How reader queues grow when full, see `Bus::set_growth()`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Growth {
+    /// Double the capacity: fewer reallocations, up to twice the memory needed
+    Doubling,
+    /// Grow to the exact length needed: less memory, a reallocation for every new message
+    Exact,
+}
+
+/// `QueueStorage` stores the pending messages of a reader, in order.
+///
+/// Readers use a `VecDeque` by default (a `SmallVec` with the `smallvec` feature); custom
+/// storages (ring buffers, instrumented queues...) can be given to `Bus::add_rx_with()`.
+pub trait QueueStorage<T> {
+    /// Number of queued messages
+    fn len(&self) -> usize;
+
+    /// Whether no message is queued
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Queue a message; the bus only calls it when the storage isn't full.
+    fn push_back(&mut self, value: T);
+
+    /// Remove the oldest message
+    fn pop_front(&mut self) -> Option<T>;
+
+    /// Move all the messages, in order, at the end of `out`
+    fn drain_into(&mut self, out: &mut Vec<T>) {
+        out.reserve(self.len());
+        while let Some(value) = self.pop_front() {
+            out.push(value);
+        }
+    }
+
+    /// Number of messages the storage can hold, if bounded
+    fn limit(&self) -> Option<usize> {
+        None
+    }
+
+    /// What to do when a message is pushed while `limit()` is reached
+    fn overflow(&self) -> Overflow {
+        Overflow::DropOldest
+    }
+
+    /// Reserve room for at least `additional` more messages
+    fn reserve(&mut self, _additional: usize) {}
+
+    /// Reserve room for exactly `additional` more messages
+    fn reserve_exact(&mut self, additional: usize) {
+        self.reserve(additional);
+    }
+
+    /// Release the unused memory
+    fn shrink_to_fit(&mut self) {}
+}
+
+/// `pop_front()` shifts all the messages: it suits queues drained at once, a `VecDeque`
+/// suits queues popped one message at a time.
+impl<T> QueueStorage<T> for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn push_back(&mut self, value: T) {
+        self.push(value);
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        if Vec::is_empty(self) {
+            return None;
+        }
+        Some(self.remove(0))
+    }
+
+    fn drain_into(&mut self, out: &mut Vec<T>) {
+        out.append(self);
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+
+    fn reserve_exact(&mut self, additional: usize) {
+        Vec::reserve_exact(self, additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        Vec::shrink_to_fit(self);
+    }
+}
+
+impl<T> QueueStorage<T> for VecDeque<T> {
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn push_back(&mut self, value: T) {
+        VecDeque::push_back(self, value);
+    }
+
+    fn pop_front(&mut self) -> Option<T> {
+        VecDeque::pop_front(self)
+    }
+
+    fn drain_into(&mut self, out: &mut Vec<T>) {
+        out.extend(self.drain(..));
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        VecDeque::reserve(self, additional);
+    }
+
+    fn reserve_exact(&mut self, additional: usize) {
+        VecDeque::reserve_exact(self, additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        VecDeque::shrink_to_fit(self);
+    }
+}
+
+/// `pop_front()` shifts all the messages, which stays cheap while they fit inline.
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array> QueueStorage<A::Item> for smallvec::SmallVec<A> {
+    fn len(&self) -> usize {
+        smallvec::SmallVec::len(self)
+    }
+
+    fn push_back(&mut self, value: A::Item) {
+        self.push(value);
+    }
+
+    fn pop_front(&mut self) -> Option<A::Item> {
+        if smallvec::SmallVec::is_empty(self) {
+            return None;
+        }
+        Some(self.remove(0))
+    }
+
+    fn drain_into(&mut self, out: &mut Vec<A::Item>) {
+        out.extend(self.drain(..));
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        smallvec::SmallVec::reserve(self, additional);
+    }
+
+    fn reserve_exact(&mut self, additional: usize) {
+        smallvec::SmallVec::reserve_exact(self, additional);
+    }
+
+    fn shrink_to_fit(&mut self) {
+        smallvec::SmallVec::shrink_to_fit(self);
+    }
+}
+
+// Storage of a reader queue: the default one, or a custom one
+pub(crate) enum Storage<T> {
+    Default(crate::Queue<T>),
+    Custom(Box<dyn QueueStorage<T>>),
+}
+impl<T> Storage<T> {
+    pub(crate) fn get(&self) -> &dyn QueueStorage<T> {
+        match self {
+            Storage::Default(queue) => queue,
+            Storage::Custom(queue) => queue.as_ref(),
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut dyn QueueStorage<T> {
+        match self {
+            Storage::Default(queue) => queue,
+            Storage::Custom(queue) => queue.as_mut(),
+        }
+    }
+
+    // Arrange the default queue so that `as_slice()` returns all of it
+    pub(crate) fn make_contiguous(&mut self) {
+        #[cfg(not(feature = "smallvec"))]
+        if let Storage::Default(queue) = self {
+            queue.make_contiguous();
+        }
+    }
+
+    // The messages of the default queue, once contiguous
+    pub(crate) fn as_slice(&self) -> Option<&[T]> {
+        match self {
+            #[cfg(not(feature = "smallvec"))]
+            Storage::Default(queue) => Some(queue.as_slices().0),
+            #[cfg(feature = "smallvec")]
+            Storage::Default(queue) => Some(queue.as_slice()),
+            Storage::Custom(_) => None,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        match self {
+            Storage::Default(queue) => queue.clear(),
+            Storage::Custom(queue) => while queue.pop_front().is_some() {},
+        }
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn exercise(storage: &mut dyn QueueStorage<u32>) {
+        assert!(storage.is_empty());
+        storage.push_back(1);
+        storage.push_back(2);
+        storage.push_back(3);
+        assert_eq!(storage.len(), 3);
+        assert_eq!(storage.pop_front(), Some(1));
+
+        let mut out = vec![0];
+        storage.drain_into(&mut out);
+        assert_eq!(out, vec![0, 2, 3]);
+        assert_eq!(storage.pop_front(), None);
+    }
+
+    #[test]
+    fn storages_should_keep_messages_in_order() {
+        exercise(&mut Vec::new());
+        exercise(&mut VecDeque::new());
+        #[cfg(feature = "smallvec")]
+        exercise(&mut smallvec::SmallVec::<[u32; 2]>::new());
+    }
+}
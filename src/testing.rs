@@ -0,0 +1,222 @@
+/*!
+Test helpers for code depending on `syncbus`.
+
+- `MockBus` is a real `Bus` which also records everything that was broadcast,
+- `assert_broadcast!(bus, value)` checks that a `MockBus` has seen a message,
+- `FakeReader` is a `BusReader` whose queue can be filled directly.
+
+```rust
+use syncbus::assert_broadcast;
+use syncbus::testing::MockBus;
+# #[derive(Copy, Clone, PartialEq, Debug)]
+# enum Value { A, B }
+
+let bus = MockBus::<Value>::new(10);
+
+bus.broadcast(Value::A);
+
+assert_broadcast!(bus, Value::A);
+assert_eq!(bus.broadcasts(), vec![Value::A]);
+```
+*/
+
+use std::cell::RefCell;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+use crate::{Bus, BusReader, Intercept, MIN_CAPACITY};
+
+/// `MockBus` wraps a `Bus` and records every broadcast message.
+/// It derefs to the `Bus` so it can be handed to the code under test.
+///
+/// Messages are recorded by an interceptor running before the ones of the code under test,
+/// so the bus readers, their counts and hooks are the ones of the code under test.
+pub struct MockBus<T: Clone> {
+    bus: Bus<T>,
+    log: Rc<RefCell<Vec<T>>>,
+}
+impl<T: Clone + 'static> MockBus<T> {
+    /// Create a new `MockBus`, with `capacity` to be 2 or more
+    pub fn new(capacity: usize) -> MockBus<T> {
+        let mut bus = Bus::new(capacity);
+        let log = Rc::new(RefCell::new(vec![]));
+        let record = Rc::clone(&log);
+        bus.add_interceptor(move |value: T| {
+            record.borrow_mut().push(value.clone());
+            Intercept::Forward(value)
+        });
+        MockBus::<T> { bus, log }
+    }
+}
+impl<T: Clone> MockBus<T> {
+    /// All the messages broadcast so far, in order.
+    pub fn broadcasts(&self) -> Vec<T> {
+        self.log.borrow().clone()
+    }
+
+    /// Forget the messages recorded so far.
+    pub fn clear(&self) {
+        self.log.borrow_mut().clear();
+    }
+}
+impl<T: Clone> Deref for MockBus<T> {
+    type Target = Bus<T>;
+
+    fn deref(&self) -> &Bus<T> {
+        &self.bus
+    }
+}
+impl<T: Clone> DerefMut for MockBus<T> {
+    fn deref_mut(&mut self) -> &mut Bus<T> {
+        &mut self.bus
+    }
+}
+
+/// `FakeReader` is a `BusReader` without a producer: push messages in its queue
+/// directly, then hand it (or `reader_mut()`) to the code under test.
+pub struct FakeReader<T: Clone> {
+    bus: Bus<T>,
+    reader: BusReader<T>,
+}
+impl<T: Clone> FakeReader<T> {
+    /// Create a `FakeReader` with an empty queue
+    pub fn new() -> FakeReader<T> {
+        let mut bus = Bus::new(MIN_CAPACITY);
+        let reader = bus.add_rx();
+        FakeReader::<T> { bus, reader }
+    }
+
+    /// Create a `FakeReader` with the values already queued
+    pub fn with(values: &[T]) -> FakeReader<T> {
+        let fake = FakeReader::new();
+        for value in values {
+            fake.push(value.clone());
+        }
+        fake
+    }
+
+    /// Queue a message for the reader.
+    pub fn push(&self, value: T) {
+        self.bus.broadcast(value);
+    }
+
+    /// Borrow the underlying `BusReader`.
+    pub fn reader_mut(&mut self) -> &mut BusReader<T> {
+        &mut self.reader
+    }
+
+    /// Release the underlying `BusReader`: it keeps its pending messages.
+    pub fn into_reader(self) -> BusReader<T> {
+        self.reader
+    }
+}
+impl<T: Clone> Default for FakeReader<T> {
+    fn default() -> FakeReader<T> {
+        FakeReader::new()
+    }
+}
+impl<T: Clone> Deref for FakeReader<T> {
+    type Target = BusReader<T>;
+
+    fn deref(&self) -> &BusReader<T> {
+        &self.reader
+    }
+}
+impl<T: Clone> DerefMut for FakeReader<T> {
+    fn deref_mut(&mut self) -> &mut BusReader<T> {
+        &mut self.reader
+    }
+}
+
+/// Assert that a `MockBus` has broadcast a value (compared with `==`).
+/// ```
+/// # use syncbus::{assert_broadcast, testing::MockBus};
+/// let bus = MockBus::<u32>::new(10);
+/// bus.broadcast(42);
+/// assert_broadcast!(bus, 42);
+/// ```
+#[macro_export]
+macro_rules! assert_broadcast {
+    ($bus:expr, $value:expr) => {{
+        let value = $value;
+        let broadcasts = $bus.broadcasts();
+        assert!(
+            broadcasts.iter().any(|msg| *msg == value),
+            "expected {:?} to be broadcast, got {:?}",
+            value,
+            broadcasts
+        );
+    }};
+    ($bus:expr, $value:expr, $($arg:tt)+) => {{
+        let value = $value;
+        assert!($bus.broadcasts().iter().any(|msg| *msg == value), $($arg)+);
+    }};
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    enum Value {
+        A,
+        B,
+    }
+
+    #[test]
+    fn mock_bus_should_record_broadcasts() {
+        let mut bus = MockBus::<Value>::new(5);
+        let mut rx = bus.add_rx();
+
+        bus.broadcast(Value::A);
+        bus.broadcast(Value::B);
+
+        assert_broadcast!(bus, Value::A);
+        assert_broadcast!(bus, Value::B);
+        assert_eq!(bus.broadcasts(), vec![Value::A, Value::B]);
+        assert_eq!(rx.recv(), vec![Value::A, Value::B]);
+
+        bus.clear();
+        assert_eq!(bus.broadcasts(), vec![]);
+    }
+
+    #[test]
+    fn mock_bus_should_not_add_a_reader() {
+        let mut bus = MockBus::<Value>::new(5);
+        let subscribed = Rc::new(RefCell::new(0));
+        let count = Rc::clone(&subscribed);
+        bus.on_subscribe(move |_| *count.borrow_mut() += 1);
+
+        assert_eq!(bus.broadcast_checked(Value::A).delivered, 0);
+        assert!(bus.try_broadcast(Value::B).is_err());
+        assert!(bus.inspect().readers.is_empty());
+        assert_eq!(bus.broadcasts(), vec![Value::A]);
+
+        let _rx = bus.add_rx();
+        assert_eq!(*subscribed.borrow(), 1);
+        assert_eq!(bus.broadcast_checked(Value::B).delivered, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected B to be broadcast")]
+    fn assert_broadcast_should_fail_when_missing() {
+        let bus = MockBus::<Value>::new(5);
+        bus.broadcast(Value::A);
+        assert_broadcast!(bus, Value::B);
+    }
+
+    #[test]
+    fn fake_reader_should_receive_pushed_values() {
+        let mut fake = FakeReader::with(&[Value::A]);
+        fake.push(Value::B);
+
+        assert_eq!(fake.recv(), vec![Value::A, Value::B]);
+        assert_eq!(fake.recv(), vec![]);
+
+        fake.push(Value::A);
+        let mut rx = fake.into_reader();
+        assert_eq!(rx.recv(), vec![Value::A]);
+    }
+}
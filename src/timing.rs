@@ -0,0 +1,294 @@
+//! Readers pacing the messages they receive over time, e.g. for scroll or resize events,
+//! and sources emitting messages at regular intervals.
+
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+use crate::{Bus, BusInner, BusReader, ReaderId, Receiver, Shared, TryRecvError};
+
+/// `Throttled` wraps a `BusReader`, yielding at most one message per interval:
+/// the latest one, the older messages of the interval are dropped.
+pub struct Throttled<T: Clone> {
+    reader: BusReader<T>,
+    interval: Duration,
+    // When the last message was yielded
+    last: Option<Instant>,
+}
+impl<T: Clone> Throttled<T> {
+    /// The identifier of the reader on its bus
+    pub fn id(&self) -> ReaderId {
+        self.reader.id()
+    }
+
+    /// The latest pending message, if the interval elapsed since the previous one.
+    pub fn poll(&mut self) -> Option<T> {
+        self.poll_at(Instant::now())
+    }
+
+    /// Like `poll()`, at the time `now`, e.g. the time of the frame.
+    pub fn poll_at(&mut self, now: Instant) -> Option<T> {
+        let last = self.last;
+        if last.is_some_and(|last| now.saturating_duration_since(last) < self.interval) {
+            return None;
+        }
+        let value = self.reader.recv().pop()?;
+        self.last = Some(now);
+        Some(value)
+    }
+
+    /// Release the underlying `BusReader`.
+    pub fn into_inner(self) -> BusReader<T> {
+        self.reader
+    }
+}
+
+impl<T: Clone> Receiver<T> for Throttled<T> {
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.poll().ok_or_else(|| closed_or_empty(&self.reader))
+    }
+
+    fn is_closed(&self) -> bool {
+        self.reader.is_closed()
+    }
+}
+
+/// `Debounced` wraps a `BusReader`, yielding the latest message once no other message
+/// arrived for a quiet period; the older messages are dropped.
+///
+/// Messages are timed when polled: poll often, e.g. every frame.
+pub struct Debounced<T: Clone> {
+    reader: BusReader<T>,
+    quiet: Duration,
+    // The latest message, and when it was received
+    latest: Option<(Instant, T)>,
+}
+impl<T: Clone> Debounced<T> {
+    /// The identifier of the reader on its bus
+    pub fn id(&self) -> ReaderId {
+        self.reader.id()
+    }
+
+    /// The latest message, if the quiet period elapsed since it was received.
+    pub fn poll(&mut self) -> Option<T> {
+        self.poll_at(Instant::now())
+    }
+
+    /// Like `poll()`, at the time `now`, e.g. the time of the frame.
+    pub fn poll_at(&mut self, now: Instant) -> Option<T> {
+        if let Some(value) = self.reader.recv().pop() {
+            self.latest = Some((now, value));
+        }
+        match &self.latest {
+            Some((at, _)) if now.saturating_duration_since(*at) >= self.quiet => {
+                self.latest.take().map(|(_, value)| value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Release the underlying `BusReader`; the message waiting for the quiet period is dropped.
+    pub fn into_inner(self) -> BusReader<T> {
+        self.reader
+    }
+}
+
+impl<T: Clone> Receiver<T> for Debounced<T> {
+    fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.poll().ok_or_else(|| closed_or_empty(&self.reader))
+    }
+
+    fn is_closed(&self) -> bool {
+        self.reader.is_closed()
+    }
+}
+
+fn closed_or_empty<T: Clone>(reader: &BusReader<T>) -> TryRecvError {
+    match reader.is_closed() {
+        true => TryRecvError::Closed,
+        false => TryRecvError::Empty,
+    }
+}
+
+impl<T: Clone> BusReader<T> {
+    /// Turn the reader into a `Throttled` reader, yielding at most one message per `interval`.
+    /// ```
+    /// # use std::time::Duration;
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// let mut scrolls = bus.add_rx().throttle(Duration::from_secs(60));
+    /// bus.broadcast(1);
+    /// bus.broadcast(2);
+    /// assert_eq!(scrolls.poll(), Some(2));
+    /// bus.broadcast(3);
+    /// assert_eq!(scrolls.poll(), None);
+    /// ```
+    pub fn throttle(self, interval: Duration) -> Throttled<T> {
+        Throttled::<T> {
+            reader: self,
+            interval,
+            last: None,
+        }
+    }
+
+    /// Turn the reader into a `Debounced` reader, yielding the latest message once
+    /// no message arrived for `quiet`.
+    /// ```
+    /// # use std::time::Duration;
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// let mut resizes = bus.add_rx().debounce(Duration::ZERO);
+    /// bus.broadcast(1);
+    /// bus.broadcast(2);
+    /// assert_eq!(resizes.poll(), Some(2));
+    /// assert_eq!(resizes.poll(), None);
+    /// ```
+    pub fn debounce(self, quiet: Duration) -> Debounced<T> {
+        Debounced::<T> {
+            reader: self,
+            quiet,
+            latest: None,
+        }
+    }
+}
+
+/// How often a `TickSource` emits.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Every {
+    /// Every n ticks of the bus (see `Bus::tick()`)
+    Ticks(u64),
+    /// Every interval of time
+    Interval(Duration),
+}
+
+/// `TickSource` broadcasts the messages of a factory on a bus at regular intervals, when
+/// pumped: once per frame, or with `web::BusDriver` (`web` feature).
+/// ```
+/// # use std::time::Instant;
+/// use syncbus::{Every, TickSource};
+///
+/// # let mut bus = syncbus::Bus::<&str>::new(10);
+/// let mut reader = bus.add_rx();
+/// let mut blink = TickSource::new(&bus, Every::Ticks(2), || "blink");
+/// bus.tick();
+/// assert!(!blink.pump(Instant::now()));
+/// bus.tick();
+/// assert!(blink.pump(Instant::now()));
+/// assert_eq!(reader.recv(), vec!["blink"]);
+/// ```
+pub struct TickSource<T: Clone> {
+    inner: Weak<Shared<T>>,
+    every: Every,
+    factory: Box<dyn FnMut() -> T>,
+    // Bus tick and time of the last message
+    last_tick: u64,
+    last_at: Instant,
+}
+impl<T: Clone> TickSource<T> {
+    /// Create a source broadcasting the messages of `factory` on `bus`, starting now.
+    pub fn new<F>(bus: &Bus<T>, every: Every, factory: F) -> TickSource<T>
+    where
+        F: FnMut() -> T + 'static,
+    {
+        TickSource::<T> {
+            inner: Rc::downgrade(&bus.inner),
+            every,
+            factory: Box::new(factory),
+            last_tick: bus.inner.borrow().tick,
+            last_at: Instant::now(),
+        }
+    }
+
+    /// Broadcast a message if one is due at the time `now`, and return whether it did;
+    /// at most one message is broadcast per call, however late it is, and none once the `Bus`
+    /// was dropped.
+    pub fn pump(&mut self, now: Instant) -> bool {
+        let inner = match self.inner.upgrade() {
+            Some(inner) if !inner.borrow().closed => inner,
+            _ => return false,
+        };
+        let due = match self.every {
+            Every::Ticks(ticks) => {
+                let tick = inner.borrow().tick;
+                let due = tick.saturating_sub(self.last_tick) >= ticks;
+                if due {
+                    self.last_tick = tick;
+                }
+                due
+            }
+            Every::Interval(interval) => {
+                let due = now.saturating_duration_since(self.last_at) >= interval;
+                if due {
+                    self.last_at = now;
+                }
+                due
+            }
+        };
+        if due {
+            BusInner::publish(&inner, (self.factory)(), None);
+        }
+        due
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Bus;
+
+    #[test]
+    fn throttled_reader_should_yield_once_per_interval() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx().throttle(Duration::from_millis(100));
+        let start = Instant::now();
+
+        bus.broadcast(1);
+        assert_eq!(rx.poll_at(start), Some(1));
+        bus.broadcast(2);
+        bus.broadcast(3);
+        assert_eq!(rx.poll_at(start + Duration::from_millis(50)), None);
+        assert_eq!(rx.poll_at(start + Duration::from_millis(100)), Some(3));
+        assert_eq!(rx.poll_at(start + Duration::from_millis(300)), None);
+    }
+
+    #[test]
+    fn debounced_reader_should_wait_for_quiet_period() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx().debounce(Duration::from_millis(100));
+        let start = Instant::now();
+
+        bus.broadcast(1);
+        assert_eq!(rx.poll_at(start), None);
+        bus.broadcast(2);
+        assert_eq!(rx.poll_at(start + Duration::from_millis(80)), None);
+        assert_eq!(rx.poll_at(start + Duration::from_millis(150)), None);
+        assert_eq!(rx.poll_at(start + Duration::from_millis(180)), Some(2));
+        assert_eq!(rx.poll_at(start + Duration::from_millis(300)), None);
+    }
+
+    #[test]
+    fn tick_source_should_emit_every_interval() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        let mut count = 0;
+        let every = Every::Interval(Duration::from_millis(100));
+        let mut source = TickSource::new(&bus, every, move || {
+            count += 1;
+            count
+        });
+        let start = Instant::now();
+
+        assert!(!source.pump(start + Duration::from_millis(50)));
+        assert!(source.pump(start + Duration::from_millis(100)));
+        assert!(!source.pump(start + Duration::from_millis(150)));
+        assert!(source.pump(start + Duration::from_millis(400)));
+        assert_eq!(rx.recv(), vec![1, 2]);
+
+        drop(bus);
+        assert!(!source.pump(start + Duration::from_millis(600)));
+    }
+}
@@ -0,0 +1,140 @@
+/*!
+Tokio integration (`tokio` feature).
+
+The bus is single-threaded, so the adapters run as local tasks (inside a `tokio::task::LocalSet`):
+
+- `spawn_forward` broadcasts on a bus the messages of a `tokio::sync::broadcast::Receiver`,
+- `spawn_mirror` polls a `BusReader` periodically and sends its messages to a `tokio::sync::broadcast::Sender`.
+
+Don't forward and mirror between the same bus and channel: messages would loop forever.
+*/
+
+use std::rc::Rc;
+use std::time::Duration;
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::{spawn_local, JoinHandle};
+
+use crate::{Bus, BusInner, BusReader};
+
+/// Spawn a local task broadcasting on `bus` the messages received by `receiver`.
+/// The task ends when the channel is closed or the bus is dropped;
+/// messages missed when the receiver lags behind are skipped.
+pub fn spawn_forward<T>(mut receiver: broadcast::Receiver<T>, bus: &Bus<T>) -> JoinHandle<()>
+where
+    T: Clone + 'static,
+{
+    let inner = Rc::downgrade(&bus.inner);
+    spawn_local(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(value) => match inner.upgrade() {
+                    Some(inner) if !inner.borrow().closed => {
+                        BusInner::publish(&inner, value, None);
+                    }
+                    _ => break,
+                },
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Spawn a local task polling `reader` every `period` and sending its messages to `sender`.
+/// The task ends once the bus is dropped and the reader drained, or when the channel has
+/// no receivers left.
+pub fn spawn_mirror<T>(
+    mut reader: BusReader<T>,
+    sender: broadcast::Sender<T>,
+    period: Duration,
+) -> JoinHandle<()>
+where
+    T: Clone + 'static,
+{
+    spawn_local(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            for value in reader.recv() {
+                if sender.send(value).is_err() {
+                    return;
+                }
+            }
+            if reader.is_closed() || sender.receiver_count() == 0 {
+                break;
+            }
+        }
+    })
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::task::LocalSet;
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    enum Value {
+        A,
+        B,
+    }
+
+    fn run(test: impl std::future::Future<Output = ()>) {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        LocalSet::new().block_on(&runtime, test);
+    }
+
+    #[test]
+    fn forward_should_broadcast_channel_messages() {
+        run(async {
+            let mut bus = Bus::<Value>::new(5);
+            let mut rx = bus.add_rx();
+            let (sender, receiver) = broadcast::channel(4);
+            let task = spawn_forward(receiver, &bus);
+
+            sender.send(Value::A).unwrap();
+            sender.send(Value::B).unwrap();
+            drop(sender);
+            task.await.unwrap();
+
+            assert_eq!(rx.recv(), vec![Value::A, Value::B]);
+        });
+    }
+
+    #[test]
+    fn mirror_should_send_bus_messages() {
+        run(async {
+            let mut bus = Bus::<Value>::new(5);
+            let (sender, mut receiver) = broadcast::channel(4);
+            let task = spawn_mirror(bus.add_rx(), sender, Duration::from_millis(1));
+
+            bus.broadcast(Value::A);
+            assert_eq!(receiver.recv().await, Ok(Value::A));
+            bus.broadcast(Value::B);
+            assert_eq!(receiver.recv().await, Ok(Value::B));
+
+            bus.broadcast(Value::A);
+            drop(bus);
+            task.await.unwrap();
+            assert_eq!(receiver.recv().await, Ok(Value::A));
+        });
+    }
+
+    #[test]
+    fn mirror_should_end_without_receivers() {
+        run(async {
+            let mut bus = Bus::<Value>::new(5);
+            let (sender, receiver) = broadcast::channel(4);
+            let task = spawn_mirror(bus.add_rx(), sender, Duration::from_millis(1));
+
+            drop(receiver);
+            task.await.unwrap();
+            assert_eq!(bus.inspect().readers.len(), 0);
+        });
+    }
+}
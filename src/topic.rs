@@ -0,0 +1,155 @@
+/*!
+Hierarchical topics.
+
+Topics are paths of levels separated by `/`, like `"ui/button/clicked"`.
+Readers created with `Bus::add_rx_topic()` subscribe to a filter, which can use
+MQTT-style wildcards:
+
+- `+` matches exactly one level: `"ui/+/clicked"` matches `"ui/button/clicked"`,
+- `#`, as the last level, matches any number of levels: `"ui/#"` matches `"ui"` and `"ui/button/clicked"`.
+
+```rust
+use syncbus::topic;
+
+assert!(topic::matches("ui/+/clicked", "ui/button/clicked"));
+assert!(topic::matches("ui/#", "ui/button/clicked"));
+assert!(!topic::matches("ui/+", "ui/button/clicked"));
+```
+*/
+
+use std::collections::BTreeMap;
+
+/// Whether the `topic` matches the subscription `filter`.
+pub fn matches(filter: &str, topic: &str) -> bool {
+    let mut filter = filter.split('/').peekable();
+    let mut topic = topic.split('/');
+    while let Some(level) = filter.next() {
+        if level == "#" && filter.peek().is_none() {
+            return true;
+        }
+        match topic.next() {
+            Some(name) if level == "+" || level == name => {}
+            _ => return false,
+        }
+    }
+    topic.next().is_none()
+}
+
+// Subscriptions indexed by filter level, so broadcasting only visits the matching branches
+#[derive(Default)]
+pub(crate) struct TopicTrie {
+    root: Node,
+}
+
+#[derive(Default)]
+struct Node {
+    // Next levels, including the `+` wildcard
+    children: BTreeMap<String, Node>,
+    // Subscribers whose filter ends at this node
+    here: Vec<u64>,
+    // Subscribers whose filter ends at this node followed by `#`
+    rest: Vec<u64>,
+}
+impl Node {
+    fn is_empty(&self) -> bool {
+        self.children.is_empty() && self.here.is_empty() && self.rest.is_empty()
+    }
+
+    fn collect(&self, levels: &[&str], out: &mut Vec<u64>) {
+        out.extend(&self.rest);
+        match levels.split_first() {
+            None => out.extend(&self.here),
+            Some((level, levels)) => {
+                if let Some(child) = self.children.get(*level) {
+                    child.collect(levels, out);
+                }
+                if *level != "+" {
+                    if let Some(child) = self.children.get("+") {
+                        child.collect(levels, out);
+                    }
+                }
+            }
+        }
+    }
+
+    fn remove(&mut self, levels: &[&str], id: u64) {
+        match levels.split_first() {
+            None => self.here.retain(|sub| *sub != id),
+            Some((&"#", [])) => self.rest.retain(|sub| *sub != id),
+            Some((level, levels)) => {
+                if let Some(child) = self.children.get_mut(*level) {
+                    child.remove(levels, id);
+                    if child.is_empty() {
+                        self.children.remove(*level);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl TopicTrie {
+    pub(crate) fn insert(&mut self, filter: &str, id: u64) {
+        let levels: Vec<&str> = filter.split('/').collect();
+        let mut node = &mut self.root;
+        for (position, level) in levels.iter().enumerate() {
+            if *level == "#" && position == levels.len() - 1 {
+                node.rest.push(id);
+                return;
+            }
+            node = node.children.entry(level.to_string()).or_default();
+        }
+        node.here.push(id);
+    }
+
+    pub(crate) fn remove(&mut self, filter: &str, id: u64) {
+        let levels: Vec<&str> = filter.split('/').collect();
+        self.root.remove(&levels, id);
+    }
+
+    // Subscribers whose filter matches the topic
+    pub(crate) fn matching(&self, topic: &str) -> Vec<u64> {
+        let levels: Vec<&str> = topic.split('/').collect();
+        let mut out = vec![];
+        self.root.collect(&levels, &mut out);
+        out
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filters_should_match_topics() {
+        assert!(matches("ui/button/clicked", "ui/button/clicked"));
+        assert!(!matches("ui/button", "ui/button/clicked"));
+        assert!(matches("ui/+/clicked", "ui/slider/clicked"));
+        assert!(!matches("ui/+/clicked", "ui/clicked"));
+        assert!(matches("ui/#", "ui"));
+        assert!(matches("ui/#", "ui/button/clicked"));
+        assert!(matches("#", "settings/volume"));
+        assert!(!matches("ui/#", "settings/volume"));
+    }
+
+    #[test]
+    fn trie_should_find_matching_subscribers() {
+        let mut trie = TopicTrie::default();
+        trie.insert("ui/button/clicked", 0);
+        trie.insert("ui/+/clicked", 1);
+        trie.insert("ui/#", 2);
+        trie.insert("settings/#", 3);
+
+        let mut found = trie.matching("ui/button/clicked");
+        found.sort_unstable();
+        assert_eq!(found, vec![0, 1, 2]);
+        assert_eq!(trie.matching("ui"), vec![2]);
+
+        trie.remove("ui/#", 2);
+        trie.remove("ui/+/clicked", 1);
+        assert_eq!(trie.matching("ui/button/clicked"), vec![0]);
+        assert_eq!(trie.matching("settings/volume"), vec![3]);
+    }
+}
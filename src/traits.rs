@@ -0,0 +1,82 @@
+//! Traits shared by the bus implementations (`Bus` and `StaticBus`).
+
+use crate::{SendError, TryRecvError};
+
+/// The producer side of a bus.
+pub trait Broadcaster<T> {
+    /// Push copies of the value in the reader queues.
+    fn broadcast(&self, value: T);
+
+    /// Push copies of the value in the reader queues; fails, giving the value back,
+    /// if there are no readers.
+    fn try_broadcast(&self, value: T) -> Result<(), SendError<T>>;
+}
+
+/// The consumer side of a bus.
+pub trait Receiver<T> {
+    /// Receive the next pending message, or tell whether the queue is just empty or
+    /// the bus was dropped.
+    fn try_recv(&mut self) -> Result<T, TryRecvError>;
+
+    /// Whether the bus was dropped; pending messages can still be received.
+    fn is_closed(&self) -> bool;
+
+    /// Receive the pending messages (if any) and empty the queue
+    fn recv(&mut self) -> Vec<T> {
+        let mut messages = vec![];
+        while let Ok(value) = self.try_recv() {
+            messages.push(value);
+        }
+        messages
+    }
+
+    /// Receive the pending messages one by one, calling `handler` for each,
+    /// and return how many were processed.
+    fn poll_each<F: FnMut(T)>(&mut self, mut handler: F) -> usize {
+        let mut count = 0;
+        while let Ok(value) = self.try_recv() {
+            handler(value);
+            count += 1;
+        }
+        count
+    }
+}
+
+/// A source of messages pumped by the main loop, whatever its message type:
+/// a `HandledReader`, or a `Bus` dispatching to its handlers.
+/// ```
+/// # let mut bus = syncbus::Bus::<u32>::new(10);
+/// # let mut other = syncbus::Bus::<&str>::new(10);
+/// use syncbus::Pollable;
+///
+/// let mut sources: Vec<Box<dyn Pollable>> = vec![
+///     Box::new(bus.add_rx().with_handler(|msg| println!("number {}", msg))),
+///     Box::new(other.add_rx().with_handler(|msg| println!("word {}", msg))),
+/// ];
+/// bus.broadcast(1);
+/// let handled: usize = sources.iter_mut().map(|source| source.pump()).sum();
+/// assert_eq!(handled, 1);
+/// ```
+pub trait Pollable {
+    /// Handle the pending messages, and return how many were handled
+    fn pump(&mut self) -> usize;
+}
+
+/// An event enum, whose variants can be subscribed to separately;
+/// implement it with `#[derive(BusEvent)]` (`derive` feature).
+pub trait BusEvent {
+    /// The name of the variant of the event
+    fn variant_name(&self) -> &'static str;
+}
+
+/// The payload of a variant of the event enum `E`, see `Bus::add_rx_variant()`.
+///
+/// With `#[derive(BusEvent)]`, it is implemented for the payload types of the single-field
+/// variants; payload types should then be unique among the variants of the enum.
+pub trait Variant<E>: Sized {
+    /// Whether the event is of this variant
+    fn matches(event: &E) -> bool;
+
+    /// The payload of the event, if of this variant
+    fn from_event(event: E) -> Option<Self>;
+}
@@ -0,0 +1,147 @@
+//! Batches of messages broadcast together, see `Bus::transaction()`.
+
+use crate::{Bus, BusInner};
+
+/// `Transaction` stages the messages of `Bus::transaction()`.
+pub struct Transaction<T: Clone> {
+    // Staged messages, with their topic
+    staged: Vec<(Option<String>, T)>,
+    rolled_back: bool,
+}
+impl<T: Clone> Transaction<T> {
+    /// Stage a message, broadcast when the transaction commits; ignored once rolled back
+    pub fn broadcast(&mut self, value: T) {
+        if !self.rolled_back {
+            self.staged.push((None, value));
+        }
+    }
+
+    /// Stage a message on `topic` (see `Bus::broadcast_topic()`); ignored once rolled back
+    pub fn broadcast_topic(&mut self, topic: &str, value: T) {
+        if !self.rolled_back {
+            self.staged.push((Some(topic.to_string()), value));
+        }
+    }
+
+    /// Discard the staged messages, and the ones staged afterwards
+    pub fn rollback(&mut self) {
+        self.staged.clear();
+        self.rolled_back = true;
+    }
+
+    /// Number of staged messages
+    pub fn len(&self) -> usize {
+        self.staged.len()
+    }
+
+    /// Whether no message is staged
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+}
+
+impl<T: Clone> Bus<T> {
+    /// Stage messages with `batch`, and broadcast them when it returns: readers see all of them,
+    /// or none if the batch (or an interceptor) panics or calls `rollback()`.
+    /// Returns whether the batch was broadcast.
+    ///
+    /// The interceptors run on all the messages before they are queued, and the hooks run once
+    /// all of them are queued.
+    /// ```
+    /// # let mut bus = syncbus::Bus::<u32>::new(10);
+    /// # let mut reader = bus.add_rx();
+    /// bus.transaction(|tx| {
+    ///     tx.broadcast(1);
+    ///     assert_eq!(reader.pending(), 0);
+    ///     tx.broadcast(2);
+    /// });
+    /// assert_eq!(reader.recv(), vec![1, 2]);
+    ///
+    /// let committed = bus.transaction(|tx| {
+    ///     tx.broadcast(3);
+    ///     tx.rollback();
+    /// });
+    /// assert!(!committed);
+    /// assert!(reader.recv().is_empty());
+    /// ```
+    pub fn transaction<F: FnOnce(&mut Transaction<T>)>(&self, batch: F) -> bool {
+        let mut tx = Transaction::<T> {
+            staged: vec![],
+            rolled_back: false,
+        };
+        batch(&mut tx);
+        if tx.rolled_back {
+            return false;
+        }
+        let mut forwarded = vec![];
+        for (topic, value) in tx.staged {
+            if let Some(value) = BusInner::intercept(&self.inner, value) {
+                forwarded.push((topic, value));
+            }
+        }
+        {
+            let mut inner = self.inner.borrow_mut();
+            for (topic, value) in forwarded {
+                inner.broadcast_except(topic.as_deref(), value, None);
+            }
+        }
+        BusInner::flush_evicted(&self.inner);
+        BusInner::apply_deferred(&self.inner);
+        true
+    }
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+
+    #[test]
+    fn transaction_should_broadcast_all_or_nothing() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            bus.transaction(|tx| {
+                tx.broadcast(1);
+                panic!("failed");
+            })
+        }));
+        assert!(result.is_err());
+        assert!(rx.recv().is_empty());
+
+        assert!(bus.transaction(|tx| {
+            tx.broadcast(2);
+            tx.broadcast(3);
+            assert_eq!(tx.len(), 2);
+        }));
+        assert_eq!(rx.recv(), vec![2, 3]);
+    }
+
+    #[test]
+    fn transaction_should_deliver_nothing_when_an_interceptor_panics() {
+        let mut bus = Bus::<u32>::new(5);
+        let mut rx = bus.add_rx();
+        bus.add_interceptor(|msg| {
+            assert!(msg < 2, "too big");
+            crate::Intercept::Forward(msg)
+        });
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            bus.transaction(|tx| {
+                tx.broadcast(1);
+                tx.broadcast(2);
+            })
+        }));
+        assert!(result.is_err());
+        assert!(rx.recv().is_empty());
+
+        assert!(!bus.transaction(|tx| {
+            tx.rollback();
+            tx.broadcast(1);
+            assert!(tx.is_empty());
+        }));
+    }
+}
@@ -0,0 +1,413 @@
+/*!
+Browser integration (`web` feature).
+
+`BusDriver` polls a reader on every animation frame and hands the non-empty batches to a
+callback, until the driver is dropped. It can also pump a `TickSource` on every frame.
+
+```rust,ignore
+let driver = BusDriver::on_raf(bus.add_rx(), |batch| {
+    for msg in batch { ... }
+});
+```
+
+`EventSource` converts DOM events into broadcasts, until dropped.
+
+```rust,ignore
+let mut source = EventSource::new();
+source
+    .keyboard(&bus, &window, |e| Some(Input::Key(e.key())))
+    .resize(&bus, &window, |_| Some(Input::Resize));
+```
+
+With the `serde` feature, `TabBridge` mirrors a bus across the browser tabs of the same origin
+with a `BroadcastChannel`, until dropped.
+
+```rust,ignore
+let bridge = TabBridge::new(&mut bus, "settings")?;
+```
+
+Also with `serde`, `Persistence` saves the pending messages of the labelled readers in
+`localStorage`, so they survive page reloads.
+
+```rust,ignore
+let mut actions = bus.add_rx();
+actions.set_label("actions");
+Persistence::restore(&bus, "app/actions")?;
+let _persistence = Persistence::on_unload(&bus, "app/actions")?;
+```
+*/
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gloo_events::EventListener;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, EventTarget, KeyboardEvent, PointerEvent};
+#[cfg(feature = "serde")]
+use {
+    serde::{de::DeserializeOwned, Serialize},
+    std::any::Any,
+    std::cell::Cell,
+    wasm_bindgen::{closure::Closure, JsValue},
+    web_sys::{BroadcastChannel, MessageEvent, Storage},
+};
+
+use crate::raf::RafLoop;
+use crate::{Bus, BusInner, BusReader, Instant, TickSource};
+#[cfg(feature = "serde")]
+use crate::{CatchUp, Intercept, RxSlot, Shared};
+
+/// `BusDriver` polls a `BusReader` (or pumps a `TickSource`) with `requestAnimationFrame`;
+/// dropping it stops the loop and unsubscribes the reader.
+pub struct BusDriver {
+    _frames: RafLoop,
+}
+impl BusDriver {
+    /// Poll `reader` every animation frame and call `callback` when messages were received.
+    pub fn on_raf<T, F>(mut reader: BusReader<T>, mut callback: F) -> BusDriver
+    where
+        T: Clone + 'static,
+        F: FnMut(Vec<T>) + 'static,
+    {
+        let frames = RafLoop::start(move || {
+            let batch = reader.recv();
+            if !batch.is_empty() {
+                callback(batch);
+            }
+        });
+        BusDriver { _frames: frames }
+    }
+
+    /// Pump `source` every animation frame.
+    pub fn pump_on_raf<T: Clone + 'static>(mut source: TickSource<T>) -> BusDriver {
+        let frames = RafLoop::start(move || {
+            source.pump(Instant::now());
+        });
+        BusDriver { _frames: frames }
+    }
+}
+
+/// `EventSource` attaches DOM event listeners broadcasting converted events on a bus.
+/// The listeners are detached when the source is dropped, and ignore the events once the `Bus`
+/// was dropped.
+#[derive(Default)]
+pub struct EventSource {
+    listeners: Vec<EventListener>,
+}
+impl EventSource {
+    /// Create an `EventSource` without listeners
+    pub fn new() -> EventSource {
+        EventSource::default()
+    }
+
+    /// Listen to `event_types` on `target`: events of type `E` are converted with `convert`
+    /// and broadcast when it returns a value.
+    pub fn listen<T, E, F>(
+        &mut self,
+        bus: &Bus<T>,
+        target: &EventTarget,
+        event_types: &[&'static str],
+        convert: F,
+    ) -> &mut EventSource
+    where
+        T: Clone + 'static,
+        E: JsCast,
+        F: FnMut(&E) -> Option<T> + 'static,
+    {
+        let convert = Rc::new(RefCell::new(convert));
+        for event_type in event_types {
+            let inner = Rc::downgrade(&bus.inner);
+            let convert = Rc::clone(&convert);
+            let listener = EventListener::new(target, *event_type, move |event| {
+                let event = event.dyn_ref::<E>();
+                if let (Some(inner), Some(event)) = (inner.upgrade(), event) {
+                    // readers alive keep the bus state after the `Bus` was dropped
+                    if inner.borrow().closed {
+                        return;
+                    }
+                    if let Some(value) = (convert.borrow_mut())(event) {
+                        BusInner::publish(&inner, value, None);
+                    }
+                }
+            });
+            self.listeners.push(listener);
+        }
+        self
+    }
+
+    /// Listen to `keydown` and `keyup` events.
+    pub fn keyboard<T, F>(
+        &mut self,
+        bus: &Bus<T>,
+        target: &EventTarget,
+        convert: F,
+    ) -> &mut EventSource
+    where
+        T: Clone + 'static,
+        F: FnMut(&KeyboardEvent) -> Option<T> + 'static,
+    {
+        self.listen(bus, target, &["keydown", "keyup"], convert)
+    }
+
+    /// Listen to `pointerdown`, `pointermove`, `pointerup` and `pointercancel` events.
+    pub fn pointer<T, F>(
+        &mut self,
+        bus: &Bus<T>,
+        target: &EventTarget,
+        convert: F,
+    ) -> &mut EventSource
+    where
+        T: Clone + 'static,
+        F: FnMut(&PointerEvent) -> Option<T> + 'static,
+    {
+        let types = ["pointerdown", "pointermove", "pointerup", "pointercancel"];
+        self.listen(bus, target, &types, convert)
+    }
+
+    /// Listen to `resize` events (usually on the `window`).
+    pub fn resize<T, F>(
+        &mut self,
+        bus: &Bus<T>,
+        target: &EventTarget,
+        convert: F,
+    ) -> &mut EventSource
+    where
+        T: Clone + 'static,
+        F: FnMut(&Event) -> Option<T> + 'static,
+    {
+        self.listen(bus, target, &["resize"], convert)
+    }
+}
+
+/// `TabBridge` mirrors the messages of a bus across the browser tabs, through a
+/// `BroadcastChannel` (`serde` feature); dropping it closes the channel.
+///
+/// The local broadcasts are posted as JSON in a microtask, so hidden tabs (which don't run
+/// animation frames) keep mirroring, and the messages posted by the other tabs are broadcast
+/// locally, but not posted back. The catch-up state of `Bus::on_subscribe_state()` isn't posted.
+#[cfg(feature = "serde")]
+pub struct TabBridge {
+    channel: BroadcastChannel,
+    _listener: EventListener,
+    // The `Outgoing` state, which the interceptor of the bus only holds weakly
+    _outgoing: Rc<dyn Any>,
+    // Removes the interceptor from the bus
+    unregister: Box<dyn Fn()>,
+}
+
+// The bridge reader, and whether a microtask is scheduled to post its messages
+#[cfg(feature = "serde")]
+struct Outgoing<T: Clone> {
+    reader: RefCell<BusReader<T>>,
+    channel: BroadcastChannel,
+    scheduled: Cell<bool>,
+}
+#[cfg(feature = "serde")]
+impl<T: Clone + Serialize> Outgoing<T> {
+    fn post(&self) {
+        self.scheduled.set(false);
+        let messages = match self.reader.try_borrow_mut() {
+            Ok(mut reader) => reader.recv(),
+            Err(_) => return,
+        };
+        for value in messages {
+            if let Ok(json) = serde_json::to_string(&value) {
+                // fails once the channel is closed
+                let _ = self.channel.post_message(&JsValue::from_str(&json));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TabBridge {
+    /// Mirror `bus` on the channel `name`; fails if the browser doesn't support `BroadcastChannel`.
+    pub fn new<T>(bus: &mut Bus<T>, name: &str) -> Result<TabBridge, JsValue>
+    where
+        T: Clone + Serialize + DeserializeOwned + 'static,
+    {
+        let channel = BroadcastChannel::new(name)?;
+        let slot = RxSlot::new(bus.inner.next_index());
+        let reader = BusInner::join(&bus.inner, slot, None, CatchUp::Nothing);
+
+        // the messages of the other tabs skip the bridge reader, so they aren't echoed
+        let (inner, except) = (Rc::downgrade(&bus.inner), reader.index);
+        let listener = EventListener::new(&channel, "message", move |event| {
+            let data = event
+                .dyn_ref::<MessageEvent>()
+                .and_then(|e| e.data().as_string());
+            let value = data.and_then(|data| serde_json::from_str::<T>(&data).ok());
+            if let (Some(inner), Some(value)) = (inner.upgrade(), value) {
+                if !inner.borrow().closed {
+                    BusInner::publish(&inner, value, Some(except));
+                }
+            }
+        });
+
+        let outgoing = Rc::new(Outgoing::<T> {
+            reader: RefCell::new(reader),
+            channel: channel.clone(),
+            scheduled: Cell::new(false),
+        });
+        // the interceptor only schedules the post, once the messages reached the bridge reader
+        let weak = Rc::downgrade(&outgoing);
+        let interceptor = bus
+            .inner
+            .borrow_mut()
+            .add_interceptor(Box::new(move |value| {
+                let outgoing = weak.upgrade();
+                if let (Some(outgoing), Some(window)) = (outgoing, web_sys::window()) {
+                    if !outgoing.scheduled.replace(true) {
+                        let weak = Rc::downgrade(&outgoing);
+                        let post = Closure::once_into_js(move || {
+                            if let Some(outgoing) = weak.upgrade() {
+                                outgoing.post();
+                            }
+                        });
+                        window.queue_microtask(post.unchecked_ref());
+                    }
+                }
+                Intercept::Forward(value)
+            }));
+        let inner = Rc::downgrade(&bus.inner);
+        let unregister = move || {
+            if let Some(inner) = inner.upgrade() {
+                BusInner::remove_interceptor(&inner, interceptor);
+            }
+        };
+        Ok(TabBridge {
+            channel,
+            _listener: listener,
+            _outgoing: outgoing,
+            unregister: Box::new(unregister),
+        })
+    }
+}
+#[cfg(feature = "serde")]
+impl Drop for TabBridge {
+    fn drop(&mut self) {
+        (self.unregister)();
+        self.channel.close();
+    }
+}
+
+/// `Persistence` saves the pending messages of the readers of a bus in `localStorage`
+/// (`serde` feature), to restore them after a page reload.
+///
+/// Reader ids change across reloads: the readers are matched by label (see
+/// `BusReader::set_label()`), and the readers without label aren't saved.
+#[cfg(feature = "serde")]
+pub struct Persistence {
+    _listener: EventListener,
+}
+#[cfg(feature = "serde")]
+impl Persistence {
+    /// Save the pending messages of the labelled readers under `key`; they stay queued.
+    pub fn save<T>(bus: &Bus<T>, key: &str) -> Result<(), JsValue>
+    where
+        T: Clone + Serialize,
+    {
+        save(&bus.inner, key)
+    }
+
+    /// Queue the messages saved under `key` in the readers of the same labels, and forget them;
+    /// returns how many messages were restored. They are queued before the pending messages,
+    /// and are older than any stamp (see `BusReader::recv_newer_than()`).
+    pub fn restore<T>(bus: &Bus<T>, key: &str) -> Result<usize, JsValue>
+    where
+        T: Clone + DeserializeOwned,
+    {
+        let storage = local_storage()?;
+        let saved = match storage.get_item(key)? {
+            Some(saved) => saved,
+            None => return Ok(0),
+        };
+        storage.remove_item(key)?;
+        let queues: Vec<(String, Vec<T>)> =
+            serde_json::from_str(&saved).map_err(|err| JsValue::from_str(&err.to_string()))?;
+
+        let mut guard = bus.inner.borrow_mut();
+        let inner = &mut *guard;
+        let mut restored = vec![];
+        let mut count = 0;
+        for (label, messages) in queues {
+            let labelled = inner.slots.iter().find(|rx| {
+                rx.label.as_deref() == Some(label.as_str()) && !restored.contains(&rx.index)
+            });
+            let index = match labelled {
+                Some(rx) => rx.index,
+                None => continue,
+            };
+            restored.push(index);
+            if let Some(rx) = inner.slots.get_mut(index) {
+                count += messages.len();
+                let pending = rx.drain_stamped();
+                for value in messages {
+                    // broadcast before the page reload, when the stamps started over
+                    rx.push(0, value, &mut inner.evicted);
+                }
+                for (stamp, value) in pending {
+                    rx.push(stamp, value, &mut inner.evicted);
+                }
+            }
+        }
+        inner.enforce_budget();
+        drop(guard);
+        BusInner::flush_evicted(&bus.inner);
+        BusInner::apply_deferred(&bus.inner);
+        Ok(count)
+    }
+
+    /// Save the pending messages under `key` when the page is unloaded (`beforeunload`),
+    /// until the `Persistence` is dropped.
+    pub fn on_unload<T>(bus: &Bus<T>, key: &str) -> Result<Persistence, JsValue>
+    where
+        T: Clone + Serialize + 'static,
+    {
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+        let (inner, key) = (Rc::downgrade(&bus.inner), key.to_string());
+        let listener = EventListener::new(&window, "beforeunload", move |_| {
+            if let Some(inner) = inner.upgrade() {
+                // the page is going away: nothing to report the error to
+                let _ = save(&inner, &key);
+            }
+        });
+        Ok(Persistence {
+            _listener: listener,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+fn local_storage() -> Result<Storage, JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+    window
+        .local_storage()?
+        .ok_or_else(|| JsValue::from_str("localStorage is not available"))
+}
+
+#[cfg(feature = "serde")]
+fn save<T: Clone + Serialize>(cell: &Shared<T>, key: &str) -> Result<(), JsValue> {
+    let queues: Vec<(String, Vec<T>)> = {
+        let mut inner = cell.borrow_mut();
+        // in delivery order, like `restore()` matches the labels
+        let indices: Vec<u64> = inner.slots.iter().map(|rx| rx.index).collect();
+        let queues = indices.into_iter().filter_map(|index| {
+            let rx = inner.slots.get_mut(index)?;
+            let label = rx.label.clone()?;
+            let messages = rx.peek_stamped().into_iter().map(|(_, value)| value);
+            Some((label, messages.collect()))
+        });
+        queues.collect()
+    };
+    let saved =
+        serde_json::to_string(&queues).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    local_storage()?.set_item(key, &saved)
+}
+
+//---------- TESTS ------------
+
+// The browser APIs don't run in the native test suite: the bus side (interceptor removal,
+// deferred changes) is tested in lib.rs. To check the bridge by hand, open the same page in
+// two tabs: a broadcast in one tab reaches the readers of the other tab once, isn't echoed back,
+// and stops being posted once the `TabBridge` is dropped.
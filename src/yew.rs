@@ -0,0 +1,81 @@
+/*!
+Yew integration (`yew` feature).
+
+`use_bus_reader` subscribes a component to a bus: the reader is polled on every animation
+frame and the component re-renders whenever messages arrive, with the messages received
+since its previous render.
+
+```rust,ignore
+#[function_component]
+fn Score(props: &Props) -> Html {
+    let bus = props.bus.clone();
+    let batch = use_bus_reader(move || bus.borrow_mut().add_rx());
+    for msg in batch.iter() { ... }
+    html! { ... }
+}
+```
+*/
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use yew::prelude::*;
+
+use crate::raf::RafLoop;
+use crate::BusReader;
+
+/// Subscribe the component with the `BusReader` returned by `subscribe` (called on first render only),
+/// and return the messages it received since the previous render (empty on unrelated re-renders).
+/// The reader is dropped, and unsubscribed, when the component unmounts.
+#[hook]
+pub fn use_bus_reader<T, F>(subscribe: F) -> Rc<Vec<T>>
+where
+    T: Clone + 'static,
+    F: FnOnce() -> BusReader<T>,
+{
+    let reader = use_mut_ref(subscribe);
+    let unrendered = use_mut_ref(Vec::<T>::new);
+    let update = use_force_update();
+    {
+        let unrendered = Rc::clone(&unrendered);
+        use_effect_with((), move |_| {
+            let poll = RafLoop::start(move || {
+                if queue_batch(&unrendered, reader.borrow_mut().recv()) {
+                    update.force_update();
+                }
+            });
+            move || drop(poll)
+        });
+    }
+    Rc::new(take_batch(&unrendered))
+}
+
+// Queue the messages until the next render, returning whether a render is needed
+fn queue_batch<T>(unrendered: &RefCell<Vec<T>>, mut messages: Vec<T>) -> bool {
+    let received = !messages.is_empty();
+    unrendered.borrow_mut().append(&mut messages);
+    received
+}
+
+// The messages queued since the previous render
+fn take_batch<T>(unrendered: &RefCell<Vec<T>>) -> Vec<T> {
+    std::mem::take(&mut *unrendered.borrow_mut())
+}
+
+//---------- TESTS ------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn batches_should_accumulate_until_rendered() {
+        let unrendered = RefCell::new(vec![]);
+        assert!(!queue_batch(&unrendered, vec![]));
+        assert!(queue_batch(&unrendered, vec![1, 2]));
+        assert!(queue_batch(&unrendered, vec![3]));
+
+        assert_eq!(take_batch(&unrendered), vec![1, 2, 3]);
+        assert_eq!(take_batch(&unrendered), Vec::<u32>::new());
+    }
+}
@@ -0,0 +1,78 @@
+/*!
+Derive macros for `syncbus`; enable them with the `derive` feature of `syncbus`.
+
+`#[derive(BusEvent)]` on an event enum implements `syncbus::BusEvent`, and `syncbus::Variant`
+for the payload types of its single-field variants, so that readers can subscribe to one
+variant with `bus.add_rx_variant::<Payload>()`.
+*/
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields};
+
+/// Implement `syncbus::BusEvent` for an enum, and `syncbus::Variant` for the payload types
+/// of its single-field variants (which should then be unique among the variants).
+#[proc_macro_derive(BusEvent)]
+pub fn derive_bus_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Error::new_spanned(&input.ident, "BusEvent can only be derived for enums")
+                .to_compile_error()
+                .into()
+        }
+    };
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let names = data.variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let label = ident.to_string();
+        match variant.fields {
+            Fields::Named(_) => quote! { #name::#ident { .. } => #label },
+            Fields::Unnamed(_) => quote! { #name::#ident(..) => #label },
+            Fields::Unit => quote! { #name::#ident => #label },
+        }
+    });
+
+    let variants = data.variants.iter().filter_map(|variant| {
+        let ident = &variant.ident;
+        let payload = match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0].ty,
+            _ => return None,
+        };
+        Some(quote! {
+            impl #impl_generics ::syncbus::Variant<#name #ty_generics> for #payload #where_clause {
+                fn matches(event: &#name #ty_generics) -> bool {
+                    match event {
+                        #name::#ident(..) => true,
+                        #[allow(unreachable_patterns)]
+                        _ => false,
+                    }
+                }
+
+                fn from_event(event: #name #ty_generics) -> ::std::option::Option<Self> {
+                    match event {
+                        #name::#ident(payload) => ::std::option::Option::Some(payload),
+                        #[allow(unreachable_patterns)]
+                        _ => ::std::option::Option::None,
+                    }
+                }
+            }
+        })
+    });
+
+    let expanded = quote! {
+        impl #impl_generics ::syncbus::BusEvent for #name #ty_generics #where_clause {
+            fn variant_name(&self) -> &'static str {
+                match self {
+                    #(#names,)*
+                }
+            }
+        }
+
+        #(#variants)*
+    };
+    expanded.into()
+}
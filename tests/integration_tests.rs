@@ -18,3 +18,36 @@ fn example() {
     assert_eq!(rx1.recv(), vec![Value::A, Value::B]);
     assert_eq!(rx2.recv(), vec![Value::A, Value::B]);
 }
+
+#[cfg(feature = "derive")]
+mod derive {
+    use syncbus::{Bus, BusEvent};
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    struct Resize(u32, u32);
+
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    struct Key(char);
+
+    #[derive(Copy, Clone, PartialEq, Debug, BusEvent)]
+    enum Event {
+        Resize(Resize),
+        Key(Key),
+        Quit,
+    }
+
+    #[test]
+    fn variant_readers_should_receive_their_payload() {
+        let mut bus = Bus::<Event>::new(10);
+        let mut resizes = bus.add_rx_variant::<Resize>();
+        let mut keys = bus.add_rx_variant::<Key>();
+
+        bus.broadcast(Event::Key(Key('a')));
+        bus.broadcast(Event::Resize(Resize(800, 600)));
+        bus.broadcast(Event::Quit);
+
+        assert_eq!(resizes.recv(), vec![Resize(800, 600)]);
+        assert_eq!(keys.recv(), vec![Key('a')]);
+        assert_eq!(Event::Quit.variant_name(), "Quit");
+    }
+}